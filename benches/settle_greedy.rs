@@ -0,0 +1,31 @@
+//! Times Ledger::settle_greedy on a ledger with 10,000 parties.
+//!
+//! Run with `cargo bench --bench settle_greedy`.
+use debtsolver::transaction;
+use debtsolver::Ledger;
+use debtsolver::Transaction;
+use std::time::Instant;
+
+const PARTIES: i32 = 10_000;
+
+fn main() {
+    let mut ledger = Ledger::new();
+    for i in 0..PARTIES {
+        let debtor = format!("P{}", i);
+        let creditor = format!("P{}", (i * 7 + 1) % PARTIES);
+        ledger
+            .add_transaction(transaction!(debtor, creditor, (i % 97 + 1, "USD")))
+            .unwrap();
+    }
+
+    let start = Instant::now();
+    let payments = ledger.settle_greedy().unwrap();
+    let elapsed = start.elapsed();
+
+    println!(
+        "settle_greedy: {} parties settled with {} payments in {:?}",
+        PARTIES,
+        payments.len(),
+        elapsed
+    );
+}
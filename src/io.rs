@@ -0,0 +1,165 @@
+//! Importing ledgers from, and exporting settlements to, CSV.
+//!
+//! Each row holds one transaction as `debtor,creditor,amount,currency`, e.g.
+//! `Alice,Bob,10.50,USD`. Fields are separated by plain commas, without quoting, so party names
+//! can't contain commas. A header row with those four column names is optional, and blank lines
+//! are skipped.
+use crate::{Ledger, Transaction, TransactionError};
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+
+const HEADER: [&str; 4] = ["debtor", "creditor", "amount", "currency"];
+
+/// Errors that can occur while importing a ledger from CSV.
+#[derive(Debug)]
+pub enum ImportError {
+    /// The CSV couldn't be read.
+    Io(std::io::Error),
+    /// The row on the given line doesn't have exactly four fields.
+    WrongFieldCount { line: usize, fields: usize },
+    /// The transaction on the given line couldn't be created or added to the ledger.
+    InvalidTransaction {
+        line: usize,
+        error: TransactionError,
+    },
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::Io(e) => Some(e),
+            ImportError::WrongFieldCount { .. } => None,
+            ImportError::InvalidTransaction { error, .. } => Some(error),
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "Could not read CSV: {}", e),
+            ImportError::WrongFieldCount { line, fields } => write!(
+                f,
+                "Line {} has {} fields, expected debtor,creditor,amount,currency",
+                line, fields
+            ),
+            ImportError::InvalidTransaction { line, error } => {
+                write!(f, "Line {}: {}", line, error)
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for ImportError {
+    fn from(err: std::io::Error) -> ImportError {
+        ImportError::Io(err)
+    }
+}
+
+impl Ledger {
+    /// Creates a ledger from CSV rows of `debtor,creditor,amount,currency`, adding each row as a
+    /// transaction. Returns an error naming the line of the first row that can't be added.
+    pub fn from_csv_reader<R: Read>(reader: R) -> Result<Ledger, ImportError> {
+        let mut ledger = Ledger::new();
+        for (index, row) in BufReader::new(reader).lines().enumerate() {
+            let row = row?;
+            let line = index + 1;
+            let fields: Vec<&str> = row.split(',').map(str::trim).collect();
+            if fields == [""] || (line == 1 && is_header(&fields)) {
+                continue;
+            }
+            if fields.len() != HEADER.len() {
+                return Err(ImportError::WrongFieldCount {
+                    line,
+                    fields: fields.len(),
+                });
+            }
+            Transaction::builder()
+                .debtor(fields[0])
+                .creditor(fields[1])
+                .amount_str(fields[2], fields[3])
+                .build()
+                .and_then(|transaction| ledger.add_transaction(transaction))
+                .map_err(|error| ImportError::InvalidTransaction { line, error })?;
+        }
+        Ok(ledger)
+    }
+}
+
+fn is_header(fields: &[&str]) -> bool {
+    fields.len() == HEADER.len()
+        && fields
+            .iter()
+            .zip(HEADER.iter())
+            .all(|(field, column)| field.eq_ignore_ascii_case(column))
+}
+
+/// Writes transactions, such as the payments from a settlement, as CSV rows of
+/// `debtor,creditor,amount,currency` under a header row.
+pub fn settlements_to_csv<W: Write>(
+    transactions: &[Transaction],
+    mut writer: W,
+) -> std::io::Result<()> {
+    writeln!(writer, "{}", HEADER.join(","))?;
+    for transaction in transactions {
+        writeln!(
+            writer,
+            "{},{},{},{}",
+            transaction.debtor,
+            transaction.creditor,
+            transaction.amount.amount(),
+            transaction.amount.currency().iso_alpha_code
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ledger_imports_from_csv_and_exports_settlement() {
+        let csv = "debtor,creditor,amount,currency\n\
+                   Alice,Bob,20.50,USD\n\
+                   \n\
+                   Bob, Charlie ,20.50,usd\n";
+        let ledger = Ledger::from_csv_reader(csv.as_bytes()).unwrap();
+        assert_eq!(ledger.history().len(), 2);
+
+        let mut output = Vec::new();
+        settlements_to_csv(&ledger.settle().unwrap(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "debtor,creditor,amount,currency\nAlice,Charlie,20.50,USD\n"
+        );
+    }
+
+    #[test]
+    fn ledger_csv_import_names_malformed_line() {
+        let csv = "Alice,Bob,10,USD\nBob,Charlie,10\n";
+        match Ledger::from_csv_reader(csv.as_bytes()) {
+            Err(e @ ImportError::WrongFieldCount { line: 2, fields: 3 }) => assert_eq!(
+                e.to_string(),
+                "Line 2 has 3 fields, expected debtor,creditor,amount,currency"
+            ),
+            other => panic!("expected a field count error, got {:?}", other),
+        }
+
+        let csv = "Alice,Bob,10,USD\nAlice,Bob,10,GBP\nBob,Alice,ten,USD\n";
+        assert!(matches!(
+            Ledger::from_csv_reader(csv.as_bytes()),
+            Err(ImportError::InvalidTransaction {
+                line: 2,
+                error: TransactionError::CurrencyMismatch(_)
+            })
+        ));
+
+        let csv = "Alice,Bob,ten,USD\n";
+        assert!(matches!(
+            Ledger::from_csv_reader(csv.as_bytes()),
+            Err(ImportError::InvalidTransaction { line: 1, .. })
+        ));
+    }
+}
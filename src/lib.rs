@@ -10,32 +10,42 @@
 //! For example, if Bob borrows 10 from Alice, you would track that as:
 //!
 //! ```edition2018
-//! transaction = transaction!("Alice", "Bob", (10, "USD")));
+//! # use debtsolver::{transaction, Transaction};
+//! let transaction = transaction!("Alice", "Bob", (10, "USD"));
 //! ```
 //!
 //! Legders are created empty, and you can add transactions to them to track the current state of
 //! debtors and creditors.   
 //!
 //! ```edition2018
-//! ledger = Ledger::new()
-//! ledger.add_transaction(transaction);
+//! # use debtsolver::{transaction, Ledger, Transaction};
+//! # let transaction = transaction!("Alice", "Bob", (10, "USD"));
+//! let mut ledger = Ledger::new();
+//! ledger.add_transaction(transaction).unwrap();
 //! ```
 //!
 //! You can inspect the state of the ledger at any point by calling to_vector on it to get the
 //! list of debtors and creditors as a vector of tuples
 //!
 //! ```edition2018
-//! for transaction in ledger.to_vector(){
-//!     println!("{}", transaction)
+//! # use debtsolver::{transaction, Ledger, Transaction};
+//! # let mut ledger = Ledger::new();
+//! # ledger.add_transaction(transaction!("Alice", "Bob", (10, "USD"))).unwrap();
+//! for (party, balance) in ledger.to_vector() {
+//!     println!("{} {}", party, balance)
 //! };
-//! // (Alice, Bob, 10 USD)
+//! // Alice -$10.00
+//! // Bob $10.00
 //! ```
 //!
 //! Once all the debts are tracked, and you want to figure out the fastest way for debtors to pay
 //! back creditors, you can simply call settle:
 //!
 //! ```edition2018
-//! let payments = ledger.settle();
+//! # use debtsolver::{transaction, Ledger, Transaction};
+//! # let mut ledger = Ledger::new();
+//! # ledger.add_transaction(transaction!("Alice", "Bob", (10, "USD"))).unwrap();
+//! let payments = ledger.settle().unwrap();
 //! ```
 //!   
 //!
@@ -52,50 +62,103 @@
 //!     // Let's say that:
 //!     // Alice paid 20 for Bob's lunch
 //!     // Bob paid 20 for Charlie's dinner the next day.
-//!     ledger.add_transaction(transaction!("Alice", "Bob", (20, "USD")));
-//!     ledger.add_transaction(transaction!("Bob", "Charlie", (20, "USD")));
+//!     ledger.add_transaction(transaction!("Alice", "Bob", (20, "USD"))).unwrap();
+//!     ledger.add_transaction(transaction!("Bob", "Charlie", (20, "USD"))).unwrap();
 //!
-//!     for payment in ledger.settle() {
+//!     // settle() leaves the ledger as it is, while consume_settle() also clears the balances
+//!     // once the payments are made.
+//!     for payment in ledger.consume_settle().unwrap() {
 //!         println!("{}", payment)
 //!     }
 //!     // Debtsolver will resolve this with one payment:
-//!     // Alice owes Charlie 20.00 USD
+//!     // Alice owes Charlie $20.00
 //!
 //!
 //!     // Now lets say that:
 //!     //   Bob paid for Alice's breakfast (20).
 //!     //   Charlie paid for Bob's lunch (50).
 //!     //   Alice paid for Charlie's dinner (35).
-//!     ledger.add_transaction(transaction!("Alice", "Bob", (20, "USD")));
-//!     ledger.add_transaction(transaction!("Bob", "Charlie", (50, "USD")));
-//!     ledger.add_transaction(transaction!("Charlie", "Alice", (35, "USD")));
+//!     ledger.add_transaction(transaction!("Alice", "Bob", (20, "USD"))).unwrap();
+//!     ledger.add_transaction(transaction!("Bob", "Charlie", (50, "USD"))).unwrap();
+//!     ledger.add_transaction(transaction!("Charlie", "Alice", (35, "USD"))).unwrap();
 //!
 //!
-//!     for payment in ledger.settle() {
+//!     for payment in ledger.settle().unwrap() {
 //!         println!("{}", payment)
 //!     }
-//!     //Debtsolver will resolve this with just two payments:
-//!     // Bob owes Alice 15.00 USD
-//!     // Bob owes Charlie 15.00 USD
+//!     // Debtsolver will resolve this with just two payments:
+//!     // Bob owes Alice $15.00
+//!     // Bob owes Charlie $15.00
+//! }
 //! ```
+#[cfg(feature = "chrono")]
+use chrono::{NaiveDate, NaiveDateTime};
 use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rust_decimal::{Decimal, RoundingStrategy};
 use rusty_money::Currency;
+use rusty_money::Iso;
 use rusty_money::Money;
-use rusty_money::money;
-use rusty_money::Iso::*;
+use rusty_money::Round;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::cmp;
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
+
+mod io;
+mod money;
+mod multi_currency;
+#[cfg(feature = "serde")]
+mod serde_money;
+mod typed;
+
+pub use crate::io::{settlements_to_csv, ImportError};
+pub use crate::money::{
+    find_currency, register_currency, AllocationStrategy, MoneyExt, MoneyParseError,
+    MAX_AMOUNT_LENGTH, MAX_MINOR_UNITS,
+};
+pub use crate::multi_currency::{ExchangeRates, MultiCurrencyLedger};
+pub use crate::typed::{CurrencyMarker, Eur, Gbp, Jpy, TypedLedger, TypedMoney, Usd};
 
 /// Represents a transaction where one party (debtor) pays another (creditor) the amount specified.
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+///
+/// Transactions are ordered by debtor, then creditor, then amount, and then by their remaining
+/// fields (whether they're settleable, id, memo and date) to break ties. Comparing the amounts of
+/// transactions in different currencies panics, like comparing `Money`, so only sort transactions
+/// in one currency, such as the payments from a settlement.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Transaction {
     debtor: String,
     creditor: String,
+    #[cfg_attr(feature = "serde", serde(with = "serde_money"))]
     amount: Money,
+    settleable: bool,
+    id: Option<String>,
+    memo: Option<String>,
+    #[cfg(feature = "chrono")]
+    date: Option<NaiveDate>,
+    #[cfg(feature = "chrono")]
+    timestamp: Option<NaiveDateTime>,
 }
 
+/// Creates a transaction from a debtor, a creditor and an amount tuple, panicking if it's invalid.
+/// The amount can be a whole number or a decimal string, followed by a currency code.
+///
+/// ```edition2018
+/// # use debtsolver::{transaction, Transaction};
+/// let lunch = transaction!("Alice", "Bob", (10, "USD"));
+/// let coffee = transaction!("Alice", "Bob", ("4.50", "USD"));
+/// assert_eq!(lunch.to_string(), "Alice owes Bob $10.00");
+/// assert_eq!(coffee.to_string(), "Alice owes Bob $4.50");
+/// ```
 #[macro_export]
 macro_rules! transaction {
     ($x:expr, $y:expr, $z:expr) => {
@@ -104,58 +167,426 @@ macro_rules! transaction {
 }
 
 impl Transaction {
-    pub fn new(debtor: String, creditor: String, amount: Money) -> Result<Self, ParseAmountError> {
+    /// Returns a builder for a transaction, which accepts the amount as Money or as a decimal
+    /// string and currency code, e.g. "10.50" and "USD".
+    pub fn builder() -> TransactionBuilder {
+        TransactionBuilder::default()
+    }
+
+    pub fn new(debtor: String, creditor: String, amount: Money) -> Result<Self, TransactionError> {
+        for party in &[&debtor, &creditor] {
+            if party.trim().is_empty() {
+                return Err(InvalidPartyError {
+                    party: party.to_string(),
+                }
+                .into());
+            }
+        }
+        if debtor == creditor {
+            return Err(SamePartyError { party: debtor }.into());
+        }
         if !amount.is_positive() {
-            return Err(ParseAmountError { amount });
+            return Err(ParseAmountError { amount }.into());
         };
         Ok(Transaction {
             debtor,
             creditor,
             amount,
+            settleable: true,
+            id: None,
+            memo: None,
+            #[cfg(feature = "chrono")]
+            date: None,
+            #[cfg(feature = "chrono")]
+            timestamp: None,
         })
     }
 
-    pub fn from_tuple(
+    /// Creates a transaction from an amount tuple of a whole number or decimal string, and a
+    /// currency code, e.g. `(10, "USD")` or `("10.50", "USD")`.
+    pub fn from_tuple<A: TupleAmount>(
         debtor: String,
         creditor: String,
-        amount: (i32, &str),
-    ) -> Result<Self, ParseAmountError> {
-        let (value, currency) = amount;
-        let money_amount = Money::from_string(value.to_string(), currency.to_string()).unwrap();
-        Transaction::new(debtor, creditor, money_amount)
+        amount: A,
+    ) -> Result<Self, TransactionError> {
+        Transaction::new(debtor, creditor, amount.into_money()?)
+    }
+
+    /// Returns a new transaction between the same parties, with the amount converted into another
+    /// currency at the given rate and rounded to that currency's minor units.
+    pub fn convert(
+        &self,
+        to: &'static Currency,
+        rate: Decimal,
+    ) -> Result<Transaction, TransactionError> {
+        let converted = Money::from_decimal(*self.amount.amount() * rate, to);
+        Transaction::new(
+            self.debtor.clone(),
+            self.creditor.clone(),
+            converted.round(to.exponent, Round::HalfEven),
+        )
+    }
+
+    /// Returns the transaction marked as settleable or not. A transaction that isn't settleable,
+    /// such as an informal "I'll get you next time", is kept in a ledger's history but doesn't
+    /// affect any balances, so it is never part of a settlement. Transactions are settleable by
+    /// default.
+    pub fn with_settleable(mut self, settleable: bool) -> Transaction {
+        self.settleable = settleable;
+        self
+    }
+
+    /// Returns true if the transaction counts towards balances and settlement.
+    pub fn settleable(&self) -> bool {
+        self.settleable
+    }
+
+    /// Returns the party who owes, or for a settlement payment, who pays.
+    pub fn debtor(&self) -> &str {
+        &self.debtor
+    }
+
+    /// Returns the party who is owed, or for a settlement payment, who is paid.
+    pub fn creditor(&self) -> &str {
+        &self.creditor
+    }
+
+    /// Returns the amount owed, which is always positive.
+    pub fn amount(&self) -> &Money {
+        &self.amount
+    }
+
+    /// Returns the transaction with an identifier, which can be used to look it up in or remove it
+    /// from a ledger. Transactions added to a ledger without one are given one automatically.
+    pub fn with_id(mut self, id: String) -> Transaction {
+        self.id = Some(id);
+        self
+    }
+
+    /// Returns the transaction's identifier, if it has one.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns the transaction with a memo describing it, e.g. "Dinner at Luigi's". Transactions
+    /// returned by a settlement never have a memo, which tells them apart from recorded expenses.
+    pub fn with_memo(mut self, memo: String) -> Transaction {
+        self.memo = Some(memo);
+        self
+    }
+
+    /// Returns the transaction's memo, if it has one.
+    pub fn memo(&self) -> Option<&str> {
+        self.memo.as_deref()
+    }
+
+    /// Returns the transaction with the date it took place on.
+    #[cfg(feature = "chrono")]
+    pub fn with_date(mut self, date: NaiveDate) -> Transaction {
+        self.date = Some(date);
+        self
+    }
+
+    /// Returns the date the transaction took place on, if one was recorded.
+    #[cfg(feature = "chrono")]
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.date
+    }
+
+    /// Returns the transaction with the moment it took place, which also sets its date.
+    #[cfg(feature = "chrono")]
+    pub fn with_timestamp(mut self, timestamp: NaiveDateTime) -> Transaction {
+        self.date = Some(timestamp.date());
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Returns the moment the transaction took place, if one was recorded.
+    #[cfg(feature = "chrono")]
+    pub fn timestamp(&self) -> Option<NaiveDateTime> {
+        self.timestamp
     }
 }
 
 impl fmt::Display for Transaction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} owes {} {}", self.debtor, self.creditor, self.amount)
+        write!(
+            f,
+            "{} owes {} {}",
+            self.debtor,
+            self.creditor,
+            self.amount.format_with_symbol()
+        )?;
+        if let Some(memo) = &self.memo {
+            write!(f, " ({})", memo)?;
+        }
+        Ok(())
+    }
+}
+
+// Money doesn't implement Hash, so transactions hash its amount and currency code themselves.
+// Equal Decimals hash the same regardless of scale, which keeps this consistent with Eq.
+impl Hash for Transaction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.debtor.hash(state);
+        self.creditor.hash(state);
+        hash_money(&self.amount, state);
+        self.settleable.hash(state);
+        self.id.hash(state);
+        self.memo.hash(state);
+        #[cfg(feature = "chrono")]
+        {
+            self.date.hash(state);
+            self.timestamp.hash(state);
+        }
+    }
+}
+
+/// An amount and currency code pair that `Transaction::from_tuple` and the `transaction!` macro
+/// accept. Implemented for a whole number, `(10, "USD")`, and a decimal string, `("10.50", "USD")`.
+pub trait TupleAmount {
+    /// Returns the amount as Money, or an error if the currency is unknown or the amount can't be
+    /// parsed or isn't positive.
+    fn into_money(self) -> Result<Money, TransactionError>;
+}
+
+impl TupleAmount for (i32, &str) {
+    fn into_money(self) -> Result<Money, TransactionError> {
+        let (value, currency) = self;
+        let currency = find_currency(currency).map_err(|_| UnknownCurrencyError {
+            code: currency.to_string(),
+        })?;
+        // Keep the currency's minor units in the scale, as parsing "10" as USD would give 10.00.
+        let mut amount = Decimal::from(value);
+        amount.rescale(currency.exponent);
+        let amount = Money::from_decimal(amount, currency);
+        if value <= 0 {
+            return Err(ParseAmountError { amount }.into());
+        }
+        Ok(amount)
+    }
+}
+
+impl TupleAmount for (&str, &str) {
+    fn into_money(self) -> Result<Money, TransactionError> {
+        let (amount, currency) = self;
+        match Money::from_string_checked(amount.to_string(), currency.to_string()) {
+            Ok(money) => Ok(money),
+            Err(MoneyParseError::UnknownCurrency) => Err(UnknownCurrencyError {
+                code: currency.to_string(),
+            }
+            .into()),
+            Err(_) => Err(InvalidAmountStringError {
+                amount: amount.to_string(),
+            }
+            .into()),
+        }
+    }
+}
+
+/// Builds a transaction one field at a time. Created by `Transaction::builder`.
+#[derive(Debug, Default)]
+pub struct TransactionBuilder {
+    debtor: Option<String>,
+    creditor: Option<String>,
+    amount: Option<Result<Money, TransactionError>>,
+}
+
+impl TransactionBuilder {
+    /// Sets the party who owes the amount.
+    pub fn debtor(mut self, debtor: &str) -> TransactionBuilder {
+        self.debtor = Some(debtor.to_string());
+        self
+    }
+
+    /// Sets the party who is owed the amount.
+    pub fn creditor(mut self, creditor: &str) -> TransactionBuilder {
+        self.creditor = Some(creditor.to_string());
+        self
+    }
+
+    /// Sets the amount owed.
+    pub fn amount(mut self, amount: Money) -> TransactionBuilder {
+        self.amount = Some(Ok(amount));
+        self
+    }
+
+    /// Sets the amount owed from a decimal string and a known or registered currency code, e.g.
+    /// "10.50" and "USD". Amounts that can't be parsed are reported by `build`.
+    pub fn amount_str(mut self, amount: &str, currency: &str) -> TransactionBuilder {
+        self.amount = Some((amount, currency).into_money());
+        self
+    }
+
+    /// Returns the transaction, or an error if a field is missing, the debtor and creditor are the
+    /// same party, or the amount isn't positive.
+    pub fn build(self) -> Result<Transaction, TransactionError> {
+        let debtor = self.debtor.ok_or(MissingFieldError { field: "debtor" })?;
+        let creditor = self
+            .creditor
+            .ok_or(MissingFieldError { field: "creditor" })?;
+        let amount = self.amount.ok_or(MissingFieldError { field: "amount" })??;
+        Transaction::new(debtor, creditor, amount)
     }
 }
 
 /// Represents a multi-party transaction where one or more parties (debtors) owes one or more
 /// parties (creditors) the amount specified.
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MultiPartyTransaction {
     debtors: Vec<String>,
     creditors: Vec<String>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_money"))]
     amount: Money,
+    debtor_weights: Vec<i32>,
+    creditor_weights: Vec<i32>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    allocation: AllocationStrategy,
 }
 
 impl MultiPartyTransaction {
+    /// Creates a multi-party transaction where the amount is split equally between the debtors and
+    /// between the creditors. There must be at least one debtor and one creditor, and no party can
+    /// be both a debtor and a creditor.
     pub fn new(
         debtors: Vec<String>,
         creditors: Vec<String>,
         amount: Money,
-    ) -> Result<Self, ParseAmountError> {
+    ) -> Result<Self, TransactionError> {
         if amount.is_negative() {
-            return Err(ParseAmountError { amount });
+            return Err(ParseAmountError { amount }.into());
         };
+        if debtors.is_empty() {
+            return Err(MissingFieldError { field: "debtors" }.into());
+        }
+        if creditors.is_empty() {
+            return Err(MissingFieldError { field: "creditors" }.into());
+        }
+        if let Some(party) = debtors.iter().find(|d| creditors.contains(d)) {
+            return Err(SamePartyError {
+                party: party.clone(),
+            }
+            .into());
+        }
+        let debtor_weights = vec![1; debtors.len()];
+        let creditor_weights = vec![1; creditors.len()];
         Ok(MultiPartyTransaction {
             debtors,
             creditors,
             amount,
+            debtor_weights,
+            creditor_weights,
+            allocation: AllocationStrategy::default(),
         })
     }
+
+    /// Creates a multi-party transaction where each debtor owes, and each creditor is owed, a
+    /// share of the amount proportional to their weight. Every weight must be a positive integer.
+    pub fn new_weighted(
+        debtors: Vec<(String, i32)>,
+        creditors: Vec<(String, i32)>,
+        amount: Money,
+    ) -> Result<Self, TransactionError> {
+        for (party, weight) in debtors.iter().chain(creditors.iter()) {
+            if *weight <= 0 {
+                return Err(InvalidWeightError {
+                    party: party.clone(),
+                    weight: *weight,
+                }
+                .into());
+            }
+        }
+        let (debtors, debtor_weights) = debtors.into_iter().unzip();
+        let (creditors, creditor_weights) = creditors.into_iter().unzip();
+        let mut transaction = MultiPartyTransaction::new(debtors, creditors, amount)?;
+        transaction.debtor_weights = debtor_weights;
+        transaction.creditor_weights = creditor_weights;
+        Ok(transaction)
+    }
+
+    /// Creates a multi-party transaction split by each party's share, e.g. 60 and 40 for a rent
+    /// split 60/40. This is the same as `new_weighted`.
+    pub fn with_shares(
+        debtors: Vec<(String, i32)>,
+        creditors: Vec<(String, i32)>,
+        amount: Money,
+    ) -> Result<Self, TransactionError> {
+        MultiPartyTransaction::new_weighted(debtors, creditors, amount)
+    }
+
+    /// Returns the parties who owe a share of the amount, in the order they were given.
+    pub fn debtors(&self) -> &[String] {
+        &self.debtors
+    }
+
+    /// Returns the parties who are owed a share of the amount, in the order they were given.
+    pub fn creditors(&self) -> &[String] {
+        &self.creditors
+    }
+
+    /// Returns the total amount split between the parties.
+    pub fn amount(&self) -> &Money {
+        &self.amount
+    }
+
+    /// Returns the weight of each debtor's share, in the same order as `debtors`.
+    pub fn debtor_weights(&self) -> &[i32] {
+        &self.debtor_weights
+    }
+
+    /// Returns the weight of each creditor's share, in the same order as `creditors`.
+    pub fn creditor_weights(&self) -> &[i32] {
+        &self.creditor_weights
+    }
+
+    /// Returns the transaction with the strategy used to hand out whatever is left over when the
+    /// amount can't be split exactly. Defaults to `AllocationStrategy::FavorLast`.
+    pub fn with_allocation(mut self, allocation: AllocationStrategy) -> MultiPartyTransaction {
+        self.allocation = allocation;
+        self
+    }
+
+    /// Converts the transaction into a simple Transaction, if it has exactly one debtor and one
+    /// creditor. Returns None otherwise, or if the amount isn't positive.
+    pub fn try_into_simple(self) -> Option<Transaction> {
+        match (self.debtors.as_slice(), self.creditors.as_slice()) {
+            ([debtor], [creditor]) => {
+                Transaction::new(debtor.clone(), creditor.clone(), self.amount).ok()
+            }
+            _ => None,
+        }
+    }
+
+    // Splits the amount between the debtors by weight, returning the share owed by each.
+    fn debtor_shares(&self) -> Vec<(String, Money)> {
+        self.shares(&self.debtors, &self.debtor_weights)
+    }
+
+    // Splits the amount between the creditors by weight, returning the share owed to each.
+    fn creditor_shares(&self) -> Vec<(String, Money)> {
+        self.shares(&self.creditors, &self.creditor_weights)
+    }
+
+    fn shares(&self, parties: &[String], weights: &[i32]) -> Vec<(String, Money)> {
+        let shares = self.amount.allocate_with(weights, self.allocation);
+        parties.iter().cloned().zip(shares).collect()
+    }
+}
+
+impl From<Transaction> for MultiPartyTransaction {
+    /// Converts a transaction into a multi-party transaction with a single debtor and creditor.
+    /// The id, memo and settleable flag aren't carried over.
+    fn from(transaction: Transaction) -> Self {
+        MultiPartyTransaction {
+            debtors: vec![transaction.debtor],
+            creditors: vec![transaction.creditor],
+            amount: transaction.amount,
+            debtor_weights: vec![1],
+            creditor_weights: vec![1],
+            allocation: AllocationStrategy::default(),
+        }
+    }
 }
 
 impl fmt::Display for MultiPartyTransaction {
@@ -164,12 +595,28 @@ impl fmt::Display for MultiPartyTransaction {
             f,
             "{} owes {} to {}",
             self.debtors.join(","),
-            self.amount,
+            self.amount.format_with_symbol(),
             self.creditors.join(","),
         )
     }
 }
 
+impl Hash for MultiPartyTransaction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.debtors.hash(state);
+        self.creditors.hash(state);
+        hash_money(&self.amount, state);
+        self.debtor_weights.hash(state);
+        self.creditor_weights.hash(state);
+        self.allocation.hash(state);
+    }
+}
+
+fn hash_money<H: Hasher>(money: &Money, state: &mut H) {
+    money.amount().hash(state);
+    money.currency().iso_alpha_code.hash(state);
+}
+
 #[derive(Debug)]
 pub struct ParseAmountError {
     amount: Money,
@@ -187,266 +634,3988 @@ impl fmt::Display for ParseAmountError {
     }
 }
 
-/// Represents a zero-sum ledger which tracks the current state of who owes money, and who is owed money.
-/// The sum of all balances must always add up to zero, since each debtor has an equivalent creditor.
+/// The most parties with a balance that `Ledger::settle_optimal` will settle, since its search
+/// takes time and memory exponential in the number of parties.
+pub const MAX_OPTIMAL_PARTIES: usize = 20;
+
+/// Errors that can occur while settling a ledger.
 #[derive(Debug)]
-pub struct Ledger {
-    map: HashMap<String, Money>,
+pub enum SettleError {
+    /// The settlement needed more payments than the n-1 worst case for n parties with a balance.
+    TooManyPayments { payments: usize, bound: usize },
+    /// The named party isn't tracked in the ledger.
+    UnknownParty(String),
+    /// The balances don't add up to zero, so the ledger can't be settled. The residual is what
+    /// the balances add up to instead.
+    Unbalanced { residual: Money },
+    /// The ledger has more parties with a balance than the settlement can handle.
+    TooManyParties { parties: usize, limit: usize },
+    /// No exchange rate was supplied between the two currencies.
+    MissingExchangeRate {
+        from: &'static Currency,
+        to: &'static Currency,
+    },
+    /// The maximum payment isn't a positive amount in the ledger's currency.
+    InvalidMaxPayment { max: Money },
+    /// The largest fraction of the total a payment can be isn't greater than 0 and at most 1.
+    InvalidMaxFraction(Decimal),
+    /// The fee is negative, or a flat fee isn't in the ledger's currency.
+    InvalidFee(FeeModel),
+    /// The threshold isn't a positive amount in the ledger's currency.
+    InvalidThreshold { threshold: Money },
 }
 
-impl Ledger {
-    /// Creates a new Ledger
-    pub fn new() -> Ledger {
-        Ledger {
-            map: HashMap::new(),
+impl Error for SettleError {}
+
+impl fmt::Display for SettleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettleError::TooManyPayments { payments, bound } => write!(
+                f,
+                "Settlement used {} payments, more than the maximum of {}",
+                payments, bound
+            ),
+            SettleError::UnknownParty(party) => {
+                write!(f, "{} is not a party in the ledger", party)
+            }
+            SettleError::Unbalanced { residual } => {
+                write!(f, "Balances add up to {} instead of zero", residual)
+            }
+            SettleError::TooManyParties { parties, limit } => write!(
+                f,
+                "Ledger has {} parties with a balance, more than the maximum of {}",
+                parties, limit
+            ),
+            SettleError::MissingExchangeRate { from, to } => write!(
+                f,
+                "No exchange rate from {} to {}",
+                from.iso_alpha_code, to.iso_alpha_code
+            ),
+            SettleError::InvalidMaxPayment { max } => write!(
+                f,
+                "Maximum payment {} must be a positive amount in the ledger's currency",
+                max
+            ),
+            SettleError::InvalidMaxFraction(fraction) => write!(
+                f,
+                "Maximum fraction {} must be greater than 0 and at most 1",
+                fraction
+            ),
+            SettleError::InvalidFee(fee) => write!(
+                f,
+                "Fee of {} must not be negative, and must be in the ledger's currency",
+                fee
+            ),
+            SettleError::InvalidThreshold { threshold } => write!(
+                f,
+                "Threshold {} must be a positive amount in the ledger's currency",
+                threshold
+            ),
         }
     }
+}
 
-    /// Accepts a transaction and updates debtor and creditor balances in the ledger.
-    pub fn add_transaction(&mut self, transaction: Transaction) {
-        *self
-            .map
-            .entry(transaction.debtor)
-            .or_insert_with(|| money!(0, "USD")) -= transaction.amount.clone();
-        *self
-            .map
-            .entry(transaction.creditor)
-            .or_insert_with(|| money!(0, "USD")) += transaction.amount.clone();
-    }
+#[derive(Debug)]
+pub struct InvalidPartyError {
+    party: String,
+}
 
-    pub fn add_multi_party_transaction(&mut self, transaction: MultiPartyTransaction) {
-        let num_debtors = transaction.debtors.len() as i32;
-        let mut debt_shares = transaction.amount.allocate_to(num_debtors).unwrap();
-        for debtor in transaction.debtors {
-            *self.map.entry(debtor).or_insert_with(|| money!(0, "USD")) -=
-                debt_shares.pop().unwrap();
-        }
+impl Error for InvalidPartyError {}
 
-        let num_creditors = transaction.creditors.len() as i32;
-        let mut credit_shares = transaction.amount.allocate_to(num_creditors).unwrap();
-        for creditor in transaction.creditors {
-            *self.map.entry(creditor).or_insert_with(|| money!(0, "USD")) +=
-                credit_shares.pop().unwrap();
-        }
+impl fmt::Display for InvalidPartyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Party name '{}' is empty", self.party)
     }
+}
 
-    /// Returns the smallest possible set of transactions that will resolve all debts.
-    pub fn settle(&mut self) -> Vec<Transaction> {
-        self.settle_upto(self.map.len() - 1)
-    }
+#[derive(Debug)]
+pub struct InvalidWeightError {
+    party: String,
+    weight: i32,
+}
 
-    /// Finds the smallest possible set of transactions that will resolve all debts, given a group size.
-    /// This ranges between n/2 (best case) and n-1 (worst case), where n is the number of
-    /// debtors and creditors.
-    pub fn settle_upto(&mut self, group_size: usize) -> Vec<Transaction> {
-        let mut payments: Vec<Transaction> = Vec::new();
-        if group_size > 0 {
-            for x in 1..=group_size {
-                payments.append(&mut self.settle_combinations(x));
-            }
-        }
-        payments.append(&mut self.clear_all_entries());
-        payments
+impl Error for InvalidWeightError {}
+
+impl fmt::Display for InvalidWeightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Weight {} for {} is not a positive integer",
+            self.weight, self.party
+        )
     }
+}
 
-    // Converts the ledger from a hashmap into a set of vector-tuples containing the
-    // debtor/creditor and the amount. Debts are negative, and credits are positive.
-    pub fn to_vector(&self) -> Vec<(String, Money)> {
-        let mut ledger_entries: Vec<(String, Money)> = Vec::new();
+#[derive(Debug)]
+pub struct UnknownCurrencyError {
+    code: String,
+}
 
-        for (key, val) in self.map.iter() {
-            ledger_entries.push((key.clone(), val.clone()));
-        }
-        ledger_entries
-    }
+impl Error for UnknownCurrencyError {}
 
-    fn panic_unless_empty(&self) {
-        for (_, val) in self.map.iter() {
-            if !val.is_zero() {
-                panic!();
-            }
-        }
+impl fmt::Display for UnknownCurrencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' is not a known or registered currency code",
+            self.code
+        )
     }
+}
 
-    // Settles combinations of a specified size. A combination is a set of ledger balances that
-    // are zero sum (add up to zero).
-    // e.g.  A = 3, B = -2 and C= -1 is a group entry of 3, since the three of them settle to 0.
-    fn settle_combinations(&mut self, combo_size: usize) -> Vec<Transaction> {
-        let mut payments: Vec<Transaction> = Vec::new();
-        let settling_combinations = self.find_zero_sum_combinations(combo_size);
+#[derive(Debug)]
+pub struct InvalidAmountStringError {
+    amount: String,
+}
 
-        for combo in settling_combinations {
-            let mut debtor_keys: Vec<String> = Vec::new();
-            let mut creditor_keys: Vec<String> = Vec::new();
-            for item in combo {
-                if item.1.is_positive() {
-                    creditor_keys.push(item.0)
-                } else if item.1.is_negative() {
-                    debtor_keys.push(item.0)
-                } else {
-                }
-            }
-            payments.append(&mut self.clear_given_keys(debtor_keys, creditor_keys));
-        }
-        payments
+impl Error for InvalidAmountStringError {}
+
+impl fmt::Display for InvalidAmountStringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a valid amount", self.amount)
     }
+}
 
-    // Settles all entries left in the ledger with a balance, in random order.
-    fn clear_all_entries(&mut self) -> Vec<Transaction> {
-        let (debtor_keys, creditor_keys) = self.debtor_and_creditor_keys();
-        let transactions = self.clear_given_keys(debtor_keys, creditor_keys);
-        self.panic_unless_empty();
-        transactions
+#[derive(Debug)]
+pub struct SamePartyError {
+    party: String,
+}
+
+impl Error for SamePartyError {}
+
+impl fmt::Display for SamePartyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} can't owe themselves", self.party)
     }
+}
 
-    // Settles a specified list of debtors and creditors, in random order.
-    fn clear_given_keys(
-        &mut self,
-        debtors: Vec<String>,
-        creditors: Vec<String>,
-    ) -> Vec<Transaction> {
-        let mut payments: Vec<Transaction> = Vec::new();
+#[derive(Debug)]
+pub struct MissingFieldError {
+    field: &'static str,
+}
 
-        for debtor in &debtors {
-            let mut debtor_amount = self.map.get(debtor).unwrap().clone();
+impl Error for MissingFieldError {}
 
-            for creditor in &creditors {
-                let mut creditor_amount = self.map.get(creditor).unwrap().clone();
+impl fmt::Display for MissingFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Transaction has no {}", self.field)
+    }
+}
 
-                // If there's still debt and credit, create a payment.
-                // If either one is missing, try grabbing another creditor
-                // If you run out of creditors, grab another debtor and start again.
-                while (creditor_amount.is_positive()) && (debtor_amount.is_negative()) {
-                    let credit_abs = creditor_amount.amount().abs();
-                    let debt_abs = debtor_amount.amount().abs();
-                    let payment_amount = cmp::min(credit_abs, debt_abs);
+#[derive(Debug)]
+pub struct CurrencyMismatchError {
+    expected: &'static Currency,
+    found: &'static Currency,
+}
 
-                    debtor_amount += Money::from_decimal(payment_amount, Currency::get(USD));
-                    self.map.insert(debtor.clone(), debtor_amount.clone());
+impl Error for CurrencyMismatchError {}
 
-                    creditor_amount -= Money::from_decimal(payment_amount, Currency::get(USD));
-                    self.map.insert(creditor.clone(), creditor_amount.clone());
+impl fmt::Display for CurrencyMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Expected an amount in {}, found {}",
+            self.expected.iso_alpha_code, self.found.iso_alpha_code
+        )
+    }
+}
 
-                    payments.push(
-                        Transaction::new(
-                            debtor.clone(),
-                            creditor.clone(),
-                            money!(payment_amount, "USD"),
-                        )
-                        .unwrap(),
-                    );
-                }
-            }
+/// Errors that can occur while creating a transaction, or adding it to a ledger.
+#[derive(Debug)]
+pub enum TransactionError {
+    InvalidAmount(ParseAmountError),
+    InvalidParty(InvalidPartyError),
+    InvalidWeight(InvalidWeightError),
+    UnknownCurrency(UnknownCurrencyError),
+    CurrencyMismatch(CurrencyMismatchError),
+    InvalidAmountString(InvalidAmountStringError),
+    SameParty(SamePartyError),
+    MissingField(MissingFieldError),
+}
+
+impl Error for TransactionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TransactionError::InvalidAmount(e) => Some(e),
+            TransactionError::InvalidParty(e) => Some(e),
+            TransactionError::InvalidWeight(e) => Some(e),
+            TransactionError::UnknownCurrency(e) => Some(e),
+            TransactionError::CurrencyMismatch(e) => Some(e),
+            TransactionError::InvalidAmountString(e) => Some(e),
+            TransactionError::SameParty(e) => Some(e),
+            TransactionError::MissingField(e) => Some(e),
         }
-        payments
     }
+}
 
-    // Finds zero sum combinations of a given size of ledger entries.
-    fn find_zero_sum_combinations(&self, combo_size: usize) -> Vec<Vec<(String, Money)>> {
-        let mut zero_sum_combinations: Vec<Vec<(String, Money)>> = Vec::new();
-        let combinations = self.to_vector().into_iter().combinations(combo_size);
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionError::InvalidAmount(e) => e.fmt(f),
+            TransactionError::InvalidParty(e) => e.fmt(f),
+            TransactionError::InvalidWeight(e) => e.fmt(f),
+            TransactionError::UnknownCurrency(e) => e.fmt(f),
+            TransactionError::CurrencyMismatch(e) => e.fmt(f),
+            TransactionError::InvalidAmountString(e) => e.fmt(f),
+            TransactionError::SameParty(e) => e.fmt(f),
+            TransactionError::MissingField(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<ParseAmountError> for TransactionError {
+    fn from(err: ParseAmountError) -> TransactionError {
+        TransactionError::InvalidAmount(err)
+    }
+}
+
+impl From<InvalidPartyError> for TransactionError {
+    fn from(err: InvalidPartyError) -> TransactionError {
+        TransactionError::InvalidParty(err)
+    }
+}
+
+impl From<InvalidWeightError> for TransactionError {
+    fn from(err: InvalidWeightError) -> TransactionError {
+        TransactionError::InvalidWeight(err)
+    }
+}
+
+impl From<UnknownCurrencyError> for TransactionError {
+    fn from(err: UnknownCurrencyError) -> TransactionError {
+        TransactionError::UnknownCurrency(err)
+    }
+}
+
+impl From<CurrencyMismatchError> for TransactionError {
+    fn from(err: CurrencyMismatchError) -> TransactionError {
+        TransactionError::CurrencyMismatch(err)
+    }
+}
+
+impl From<InvalidAmountStringError> for TransactionError {
+    fn from(err: InvalidAmountStringError) -> TransactionError {
+        TransactionError::InvalidAmountString(err)
+    }
+}
+
+impl From<SamePartyError> for TransactionError {
+    fn from(err: SamePartyError) -> TransactionError {
+        TransactionError::SameParty(err)
+    }
+}
+
+impl From<MissingFieldError> for TransactionError {
+    fn from(err: MissingFieldError) -> TransactionError {
+        TransactionError::MissingField(err)
+    }
+}
+
+/// Summarizes how many payments settlement needed across several trials with the parties in
+/// different orders.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettleStats {
+    pub trials: usize,
+    pub min_payments: usize,
+    pub max_payments: usize,
+    pub mean_payments: f64,
+}
+
+/// Summarizes a settlement, e.g. to show "3 people settled with 2 payments, $30.00 moved".
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettleSummary {
+    /// The number of parties with a balance before settling.
+    pub input_party_count: usize,
+    pub payment_count: usize,
+    /// The total of every payment.
+    pub total_transferred: Money,
+    pub largest_payment: Option<Money>,
+}
+
+/// The fee charged for each settlement payment, e.g. by a bank or payment app.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FeeModel {
+    /// The same amount is charged for every payment, whatever its size.
+    Flat(Money),
+    /// A percentage of each payment is charged, e.g. `Decimal::new(25, 1)` for 2.5%. Each
+    /// payment's fee is rounded to the currency's minor units.
+    Percentage(Decimal),
+}
+
+impl fmt::Display for FeeModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeModel::Flat(fee) => write!(f, "{} per payment", fee.format_with_symbol()),
+            FeeModel::Percentage(percent) => write!(f, "{}% of each payment", percent),
+        }
+    }
+}
+
+/// Represents one step of a settlement, for walking through it payment by payment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettleStep {
+    /// The payment made in this step, or None for the step showing the starting balances.
+    pub payment: Option<Transaction>,
+    /// Every party's balance after this step, sorted by party.
+    pub balances: Vec<(String, Money)>,
+}
+
+/// Bundles everything needed to report on a settled ledger, such as in an email at the end of a
+/// trip. With the `serde` feature the report can be serialized.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FullReport {
+    /// When the report was generated.
+    pub generated_at: SystemTime,
+    /// Every party's balance before settlement, sorted by party.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "serde_money::serialize_balances")
+    )]
+    pub balances: Vec<(String, Money)>,
+    /// The payments that settle the balances.
+    pub payments: Vec<Transaction>,
+    /// The total owed by all debtors, or None if nobody owed anything.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "serde_money::serialize_option")
+    )]
+    pub total_debt: Option<Money>,
+    /// The number of payments in the settlement.
+    pub payment_count: usize,
+    /// The ledger's metadata.
+    pub metadata: HashMap<String, String>,
+}
+
+/// A copy of a ledger's state taken by `Ledger::snapshot`, which `Ledger::restore` rolls back to,
+/// e.g. to undo an edit. It owns its copy, so the ledger can keep changing after it's taken.
+#[derive(Clone, Debug)]
+pub struct LedgerSnapshot {
+    ledger: Ledger,
+}
+
+impl LedgerSnapshot {
+    /// Returns the ledger as it was when the snapshot was taken.
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+}
+
+/// Represents a zero-sum ledger which tracks the current state of who owes money, and who is owed money.
+/// The sum of all balances must always add up to zero, since each debtor has an equivalent creditor.
+///
+/// A ledger tracks a single currency, taken from the first transaction added to it.
+///
+/// With the `serde` feature a ledger can be serialized, with its balances, history and metadata,
+/// and deserialized again later.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ledger {
+    #[cfg_attr(feature = "serde", serde(with = "serde_money::map"))]
+    map: HashMap<String, Money>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_money::currency"))]
+    currency: Option<&'static Currency>,
+    history: Vec<Transaction>,
+    multi_party_history: Vec<MultiPartyTransaction>,
+    metadata: HashMap<String, String>,
+    assigned_ids: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    seed: Option<u64>,
+}
+
+impl Ledger {
+    /// Creates a new Ledger
+    pub fn new() -> Ledger {
+        Ledger {
+            map: HashMap::new(),
+            currency: None,
+            history: Vec::new(),
+            multi_party_history: Vec::new(),
+            metadata: HashMap::new(),
+            assigned_ids: 0,
+            seed: None,
+        }
+    }
+
+    /// Creates a new Ledger that settles the debtors and creditors left after matching groups in
+    /// an order shuffled by the seed, rather than in order of their names. The same seed always
+    /// gives the same settlement for the same balances, with the same version of `rand`, so
+    /// different seeds can be used to sample different valid settlements reproducibly.
+    pub fn with_seed(seed: u64) -> Ledger {
+        Ledger {
+            seed: Some(seed),
+            ..Ledger::new()
+        }
+    }
+
+    /// Accepts a transaction and updates debtor and creditor balances in the ledger. Transactions
+    /// that aren't settleable are recorded in the history without updating any balances.
+    ///
+    /// A transaction without an id is given the next number in sequence ("1", "2", ...) as its id.
+    /// Returns an error, without adding the transaction, if it isn't in the ledger's currency.
+    pub fn add_transaction(
+        &mut self,
+        mut transaction: Transaction,
+    ) -> Result<(), TransactionError> {
+        self.check_currency(transaction.amount.currency())?;
+        if transaction.id.is_none() {
+            self.assigned_ids += 1;
+            transaction.id = Some(self.assigned_ids.to_string());
+        }
+        self.apply_transaction(&transaction);
+        self.history.push(transaction);
+        Ok(())
+    }
+
+    /// Adds a transaction like `add_transaction`, but borrows it so the caller can keep it, e.g.
+    /// in an audit log. The ledger stores its own copy in the history, and only that copy is
+    /// given an id.
+    pub fn add_transaction_ref(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<(), TransactionError> {
+        self.add_transaction(transaction.clone())
+    }
+
+    /// Accepts a transaction, records the moment it took place and adds it to the ledger.
+    #[cfg(feature = "chrono")]
+    pub fn add_transaction_at(
+        &mut self,
+        transaction: Transaction,
+        timestamp: NaiveDateTime,
+    ) -> Result<(), TransactionError> {
+        self.add_transaction(transaction.with_timestamp(timestamp))
+    }
+
+    /// Returns the first transaction in the history with the given id.
+    pub fn get_transaction(&self, id: &str) -> Option<&Transaction> {
+        self.history.iter().find(|t| t.id() == Some(id))
+    }
+
+    /// Removes the first transaction with the given id from the history, reverses its effect on
+    /// the balances and returns it. Returns None if no transaction has the id.
+    pub fn remove_transaction_by_id(&mut self, id: &str) -> Option<Transaction> {
+        let index = self.history.iter().position(|t| t.id() == Some(id))?;
+        let transaction = self.history.remove(index);
+        self.unapply_transaction(&transaction);
+        Some(transaction)
+    }
+
+    /// Reverses the effect of a transaction on the balances, crediting the debtor and debiting the
+    /// creditor, and removes the first matching transaction from the history. A transaction
+    /// without an id matches any id. Parties whose balances return to exactly zero are removed
+    /// from the ledger.
+    ///
+    /// Returns an error, without changing the ledger, if the transaction isn't in the ledger's
+    /// currency.
+    pub fn remove_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<(), TransactionError> {
+        self.check_currency(transaction.amount.currency())?;
+        let matches = |t: &Transaction| {
+            t.debtor == transaction.debtor
+                && t.creditor == transaction.creditor
+                && t.amount == transaction.amount
+                && t.settleable == transaction.settleable
+                && transaction
+                    .id
+                    .as_ref()
+                    .is_none_or(|id| t.id.as_ref() == Some(id))
+        };
+        if let Some(index) = self.history.iter().position(matches) {
+            self.history.remove(index);
+        }
+        self.unapply_transaction(transaction);
+        Ok(())
+    }
+
+    /// Reverses the effect of a multi-party transaction on the balances, and removes the first
+    /// matching transaction from the history. Parties whose balances return to exactly zero are
+    /// removed from the ledger.
+    ///
+    /// Returns an error, without changing the ledger, if the transaction isn't in the ledger's
+    /// currency.
+    pub fn remove_multi_party_transaction(
+        &mut self,
+        transaction: &MultiPartyTransaction,
+    ) -> Result<(), TransactionError> {
+        self.check_currency(transaction.amount.currency())?;
+        if let Some(index) = self
+            .multi_party_history
+            .iter()
+            .position(|t| t == transaction)
+        {
+            self.multi_party_history.remove(index);
+        }
+        let currency = transaction.amount.currency();
+        for (debtor, share) in transaction.debtor_shares() {
+            self.adjust_balance(debtor, share, currency);
+        }
+        for (creditor, share) in transaction.creditor_shares() {
+            self.adjust_balance(creditor, share.negated(), currency);
+        }
+        Ok(())
+    }
+
+    /// Creates a ledger holding copies of the given transactions, e.g. a list of expenses that
+    /// should keep being used elsewhere. Returns an error if any of them can't be added.
+    pub fn from_transactions(transactions: &[Transaction]) -> Result<Ledger, TransactionError> {
+        let mut ledger = Ledger::new();
+        ledger.add_transactions(transactions.iter().cloned())?;
+        Ok(ledger)
+    }
+
+    /// Accepts a batch of transactions and adds each of them to the ledger. Stops at the first
+    /// transaction that can't be added and returns its error, keeping the ones added before it.
+    pub fn add_transactions<I: IntoIterator<Item = Transaction>>(
+        &mut self,
+        txns: I,
+    ) -> Result<(), TransactionError> {
+        for transaction in txns {
+            self.add_transaction(transaction)?;
+        }
+        Ok(())
+    }
+
+    /// Records a refund against an earlier transaction in which `debtor` owed `creditor`,
+    /// reducing what the debtor owes by the amount. The refund is stored in the history as a
+    /// transaction in the opposite direction, from the creditor to the debtor, so its amount
+    /// stays positive. Returns the same errors as `Transaction::new` and `add_transaction`.
+    pub fn add_refund(
+        &mut self,
+        debtor: String,
+        creditor: String,
+        amount: Money,
+    ) -> Result<(), TransactionError> {
+        self.add_transaction(Transaction::new(creditor, debtor, amount)?)
+    }
+
+    /// Takes a snapshot of the ledger's balances, history and metadata, which `restore` can roll
+    /// back to later.
+    pub fn snapshot(&self) -> LedgerSnapshot {
+        LedgerSnapshot {
+            ledger: self.clone(),
+        }
+    }
+
+    /// Rolls the ledger back to a snapshot, replacing its balances, history and metadata. The
+    /// snapshot doesn't have to come from this ledger.
+    pub fn restore(&mut self, snapshot: LedgerSnapshot) {
+        *self = snapshot.ledger;
+    }
+
+    /// Applies transactions as payments that have been made, so each debtor's balance rises and
+    /// each creditor's falls by the amount, without recording them in the history. Returns an
+    /// error, without applying anything, if any transaction isn't in the ledger's currency.
+    ///
+    /// This replays a settlement: applying the payments from `settle` to the ledger leaves every
+    /// balance at zero, just as `consume_settle` would.
+    pub fn apply(&mut self, txns: &[Transaction]) -> Result<(), TransactionError> {
+        if let Some(first) = txns.first() {
+            let expected = self.currency.unwrap_or_else(|| first.amount.currency());
+            if let Some(transaction) = txns.iter().find(|t| t.amount.currency() != expected) {
+                return Err(CurrencyMismatchError {
+                    expected,
+                    found: transaction.amount.currency(),
+                }
+                .into());
+            }
+            self.check_currency(expected)?;
+        }
+        for transaction in txns {
+            self.unapply_transaction(transaction);
+        }
+        Ok(())
+    }
+
+    /// Accepts a multi-party transaction and updates every debtor and creditor balance in the
+    /// ledger. Returns an error, without adding the transaction, if it isn't in the ledger's
+    /// currency.
+    pub fn add_multi_party_transaction(
+        &mut self,
+        transaction: MultiPartyTransaction,
+    ) -> Result<(), TransactionError> {
+        self.check_currency(transaction.amount.currency())?;
+        self.apply_multi_party_transaction(&transaction);
+        self.multi_party_history.push(transaction);
+        Ok(())
+    }
+
+    /// Accepts a batch of multi-party transactions and adds each of them to the ledger. Stops at
+    /// the first transaction that can't be added and returns its error, keeping the ones added
+    /// before it.
+    pub fn add_multi_party_transactions<I: IntoIterator<Item = MultiPartyTransaction>>(
+        &mut self,
+        txns: I,
+    ) -> Result<(), TransactionError> {
+        for transaction in txns {
+            self.add_multi_party_transaction(transaction)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the currency the ledger tracks, or None if no transactions have been added yet.
+    pub fn currency(&self) -> Option<&'static Currency> {
+        self.currency
+    }
+
+    /// Returns the smallest possible set of transactions that will resolve all debts, or an error
+    /// if the balances don't add up to zero. The payments are worked out on a copy of the
+    /// balances, so the ledger is left unchanged and can keep taking transactions. The other
+    /// `settle_*` methods work on a copy too; only `consume_settle` clears the balances.
+    pub fn settle(&self) -> Result<Vec<Transaction>, SettleError> {
+        self.scratch().consume_settle()
+    }
+
+    /// Settles the ledger like `settle`, but clears the balances as it goes, leaving every
+    /// party's balance at zero once the payments are returned. The history is kept.
+    pub fn consume_settle(&mut self) -> Result<Vec<Transaction>, SettleError> {
+        // With one debtor and one creditor there is only one possible payment, so skip the search.
+        if self.map.values().filter(|v| !v.is_zero()).count() <= 2 {
+            self.check_balanced()?;
+            return Ok(self.clear_all_entries());
+        }
+        self.clear_upto(self.map.len().saturating_sub(1))
+    }
+
+    /// Settles the ledger like `settle`, and returns the payments sorted by debtor, then creditor,
+    /// then amount, which is the same order `Transaction`'s `Ord` gives settlement payments.
+    pub fn settle_sorted(&self) -> Result<Vec<Transaction>, SettleError> {
+        let mut payments = self.settle()?;
+        payments.sort_by(|a, b| {
+            a.debtor
+                .cmp(&b.debtor)
+                .then_with(|| a.creditor.cmp(&b.creditor))
+                .then_with(|| a.amount.amount().cmp(b.amount.amount()))
+        });
+        Ok(payments)
+    }
+
+    /// Settles the ledger like `settle`, but panics if the balances don't add up to zero.
+    pub fn settle_unchecked(&self) -> Vec<Transaction> {
+        self.settle().unwrap()
+    }
+
+    /// Settles as much of the ledger as possible, even if the balances don't add up to zero, and
+    /// returns the payments along with every balance that couldn't be settled.
+    ///
+    /// A balanced ledger settles exactly like `settle`, with no residuals. Otherwise, zero-sum
+    /// groups are settled first and the remaining debtors pay the remaining creditors until one
+    /// side runs out. The residual balances are returned for manual review, and the ledger itself
+    /// is left unchanged.
+    pub fn settle_lenient(&self) -> (Vec<Transaction>, Vec<(String, Money)>) {
+        if self.check_balanced().is_ok() {
+            let payments = self.settle().expect("balanced ledgers always settle");
+            return (payments, Vec::new());
+        }
+        let mut scratch = self.scratch();
+        let mut payments: Vec<Transaction> = Vec::new();
+        for x in 1..scratch.map.len() {
+            payments.append(&mut scratch.settle_combinations(x));
+        }
+        let (debtors, creditors) = scratch.debtor_and_creditor_keys();
+        payments.append(&mut scratch.clear_given_keys(debtors, creditors));
+        let residuals = scratch
+            .to_vector()
+            .into_iter()
+            .filter(|(_, balance)| !balance.is_zero())
+            .collect();
+        (payments, residuals)
+    }
+
+    /// Settles the ledger like `settle`, but splits any payment larger than `max` into several
+    /// payments between the same debtor and creditor. Every payment but the last is exactly
+    /// `max`, and the last carries the remainder. Returns an error if `max` isn't a positive
+    /// amount in the ledger's currency, or if the balances don't add up to zero.
+    pub fn settle_with_max(&self, max: Money) -> Result<Vec<Transaction>, SettleError> {
+        let same_currency = self.currency.is_none_or(|c| c == max.currency());
+        if !max.is_positive() || !same_currency {
+            return Err(SettleError::InvalidMaxPayment { max });
+        }
+        Ok(split_payments(self.settle()?, &max))
+    }
+
+    /// Settles the ledger like `settle`, and returns the payments with the total fee they incur
+    /// under the fee model.
+    ///
+    /// Both models are minimized by the same settlement. A flat fee costs the same for every
+    /// payment, so the cheapest settlement is the one with the fewest payments, which is what
+    /// `settle` looks for. A percentage fee depends only on the total moved, and every settlement
+    /// that has debtors pay creditors directly moves exactly the total debt, which is the least
+    /// possible, so `settle` is also the cheapest there, give or take the rounding of each
+    /// payment's fee. Returns an error if the fee is invalid or the balances don't add up to zero.
+    /// A ledger that hasn't taken a transaction yet has no currency, so it accepts a flat fee in
+    /// any currency.
+    pub fn settle_with_fees(
+        &self,
+        fee: FeeModel,
+    ) -> Result<(Vec<Transaction>, Money), SettleError> {
+        let currency = match (self.currency, &fee) {
+            (Some(currency), _) => currency,
+            (None, FeeModel::Flat(amount)) => amount.currency(),
+            (None, FeeModel::Percentage(_)) => Currency::get(Iso::USD),
+        };
+        let valid = match &fee {
+            FeeModel::Flat(amount) => !amount.is_negative() && amount.currency() == currency,
+            FeeModel::Percentage(percent) => !percent.is_sign_negative(),
+        };
+        if !valid {
+            return Err(SettleError::InvalidFee(fee));
+        }
+        let payments = self.settle()?;
+        let fees = payments.iter().map(|payment| match &fee {
+            FeeModel::Flat(amount) => amount.clone(),
+            FeeModel::Percentage(percent) => {
+                payment.amount.mul_rounded(*percent / Decimal::ONE_HUNDRED)
+            }
+        });
+        let total = Money::try_sum(currency, fees).expect("fees are in the ledger's currency");
+        Ok((payments, total))
+    }
+
+    /// Settles the ledger like `settle`, but spreads the load so that no single payment is more
+    /// than `max_fraction` of the total moved. Larger payments are split into several payments
+    /// between the same debtor and creditor, each at most the cap except the last, which carries
+    /// the remainder. The cap is rounded down to the currency's minor units, but is never less
+    /// than one minor unit.
+    ///
+    /// This trades payment count for payment size: a cap of 0.25 can need several times as many
+    /// payments as `settle`. Payments are never routed through other parties, since that would
+    /// have them pass on money they don't owe. Returns an error if `max_fraction` isn't greater
+    /// than 0 and at most 1, or if the balances don't add up to zero.
+    pub fn settle_balanced(&self, max_fraction: Decimal) -> Result<Vec<Transaction>, SettleError> {
+        if max_fraction <= Decimal::ZERO || max_fraction > Decimal::ONE {
+            return Err(SettleError::InvalidMaxFraction(max_fraction));
+        }
+        let payments = self.settle()?;
+        let currency = match payments.first() {
+            Some(payment) => payment.amount.currency(),
+            None => return Ok(payments),
+        };
+        let total = Money::try_sum(currency, payments.iter().map(|p| p.amount.clone()))
+            .expect("settlement payments are all in the ledger's currency");
+        let cap = (*total.amount() * max_fraction)
+            .round_dp_with_strategy(currency.exponent, RoundingStrategy::ToZero);
+        let smallest = Money::from_minor(1, currency);
+        let cap = Money::from_decimal(cap, currency);
+        let cap = if cap.amount() < smallest.amount() {
+            smallest
+        } else {
+            cap
+        };
+        Ok(split_payments(payments, &cap))
+    }
+
+    /// Settles the ledger like `settle`, but verifies that no more than n-1 payments were needed,
+    /// where n is the number of parties with a balance.
+    pub fn settle_checked(&self) -> Result<Vec<Transaction>, SettleError> {
+        let parties = self.map.values().filter(|v| !v.is_zero()).count();
+        let bound = parties.saturating_sub(1);
+        let payments = self.settle()?;
+        if payments.len() > bound {
+            return Err(SettleError::TooManyPayments {
+                payments: payments.len(),
+                bound,
+            });
+        }
+        Ok(payments)
+    }
+
+    /// Settles the ledger like `settle`, but returns it as a series of steps. The first step shows
+    /// the starting balances, and every following step shows one payment and the balances left
+    /// after it, ending with every balance at zero.
+    pub fn settle_steps(&self) -> Result<Vec<SettleStep>, SettleError> {
+        let mut balances = self.to_vector();
+        balances.sort();
+        let mut steps = vec![SettleStep {
+            payment: None,
+            balances: balances.clone(),
+        }];
+        for payment in self.settle()? {
+            for (party, balance) in balances.iter_mut() {
+                if *party == payment.debtor {
+                    *balance += payment.amount.clone();
+                } else if *party == payment.creditor {
+                    *balance -= payment.amount.clone();
+                }
+            }
+            steps.push(SettleStep {
+                payment: Some(payment),
+                balances: balances.clone(),
+            });
+        }
+        Ok(steps)
+    }
+
+    /// Settles the ledger like `settle`, but groups the payments into batches where no party
+    /// appears twice in the same batch, so each batch can be executed in parallel. A party can
+    /// appear in several batches, so each batch should complete before the next one starts.
+    pub fn settle_concurrent(&self) -> Result<Vec<Vec<Transaction>>, SettleError> {
+        let mut batches: Vec<Vec<Transaction>> = Vec::new();
+        for payment in self.settle()? {
+            let shares_party = |batch: &Vec<Transaction>| {
+                batch.iter().any(|other| {
+                    [&other.debtor, &other.creditor]
+                        .iter()
+                        .any(|party| **party == payment.debtor || **party == payment.creditor)
+                })
+            };
+            match batches.iter_mut().find(|batch| !shares_party(batch)) {
+                Some(batch) => batch.push(payment),
+                None => batches.push(vec![payment]),
+            }
+        }
+        Ok(batches)
+    }
+
+    /// Settles the ledger like `settle`, and returns the payments sorted and formatted one per
+    /// line, ready to print. Returns "All settled up." if no payments are needed.
+    pub fn settle_to_string(&self) -> Result<String, SettleError> {
+        let mut payments = self.settle()?;
+        if payments.is_empty() {
+            return Ok("All settled up.".to_string());
+        }
+        payments.sort();
+        Ok(payments.iter().map(Transaction::to_string).join("\n"))
+    }
+
+    /// Settles the ledger like `settle`, and returns each payment as a `(from, to, amount)` edge,
+    /// ready to draw as a directed graph of who pays whom. See also `settlement_to_dot`.
+    pub fn settle_to_edges(&self) -> Result<Vec<(String, String, Money)>, SettleError> {
+        Ok(self
+            .settle()?
+            .into_iter()
+            .map(|payment| (payment.debtor, payment.creditor, payment.amount))
+            .collect())
+    }
+
+    /// Settles the ledger like `settle`, and returns the payments in a report together with the
+    /// balances they settled, the total debt and the ledger's metadata.
+    pub fn full_report(&self) -> Result<FullReport, SettleError> {
+        let mut balances = self.to_vector();
+        balances.sort();
+        let payments = self.settle()?;
+        // Every debtor pays off exactly what they owe, so the payments add up to the total debt.
+        let total_debt = payments
+            .iter()
+            .map(|payment| payment.amount.clone())
+            .reduce(|total, amount| total + amount);
+        Ok(FullReport {
+            generated_at: SystemTime::now(),
+            balances,
+            payment_count: payments.len(),
+            payments,
+            total_debt,
+            metadata: self.metadata.clone(),
+        })
+    }
+
+    /// Settles copies of the ledger `trials` times, with the parties in a different random order
+    /// each time, and reports the fewest, most and average number of payments needed. The ledger
+    /// itself is left unchanged.
+    pub fn settle_stats(&self, trials: usize) -> Result<SettleStats, SettleError> {
+        self.check_balanced()?;
+        let mut rng = rand::thread_rng();
+        let mut entries = self.to_vector();
+        let mut counts: Vec<usize> = Vec::new();
+        for _ in 0..trials {
+            entries.shuffle(&mut rng);
+            // Settlement visits parties in name order, so rename them to follow the shuffle.
+            let mut trial = Ledger::new();
+            trial.map.extend(
+                entries
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (_, balance))| (format!("{:08}", i), balance.clone())),
+            );
+            counts.push(trial.settle()?.len());
+        }
+        Ok(SettleStats {
+            trials,
+            min_payments: counts.iter().copied().min().unwrap_or(0),
+            max_payments: counts.iter().copied().max().unwrap_or(0),
+            mean_payments: counts.iter().sum::<usize>() as f64 / cmp::max(trials, 1) as f64,
+        })
+    }
+
+    /// Settles the ledger like `settle`, and also returns a lower bound on the number of payments
+    /// any settlement could use, so callers can tell how close to minimal the result is.
+    pub fn settle_with_bound(&self) -> Result<(Vec<Transaction>, usize), SettleError> {
+        let bound = self.min_payments_lower_bound();
+        Ok((self.settle()?, bound))
+    }
+
+    /// Settles the ledger like `settle`, and also returns a summary of the settlement.
+    pub fn settle_with_summary(&self) -> Result<(Vec<Transaction>, SettleSummary), SettleError> {
+        let input_party_count = self.map.values().filter(|v| !v.is_zero()).count();
+        let zero = Money::from_major(0, self.currency.unwrap_or_else(|| Currency::get(Iso::USD)));
+        let payments = self.settle()?;
+        let summary = SettleSummary {
+            input_party_count,
+            payment_count: payments.len(),
+            total_transferred: payments
+                .iter()
+                .fold(zero, |total, p| total + p.amount.clone()),
+            largest_payment: max_payment(&payments).map(|p| p.amount.clone()),
+        };
+        Ok((payments, summary))
+    }
+
+    /// Returns a lower bound on the number of payments needed to settle the ledger. Every debtor
+    /// has to make at least one payment and every creditor has to receive one, so no settlement
+    /// can use fewer payments than the larger of the two groups.
+    pub fn min_payments_lower_bound(&self) -> usize {
+        let (debtors, creditors) = self.debtor_and_creditor_keys();
+        cmp::max(debtors.len(), creditors.len())
+    }
+
+    /// Settles the ledger through a single party acting as a clearing house. Every other debtor
+    /// pays the named party, and the named party pays every other creditor, which leaves its own
+    /// balance at zero. Returns an error if the named party isn't in the ledger, or if the
+    /// balances don't add up to zero.
+    pub fn settle_to_creditor(&self, creditor: &str) -> Result<Vec<Transaction>, SettleError> {
+        if !self.map.contains_key(creditor) {
+            return Err(SettleError::UnknownParty(creditor.to_string()));
+        }
+        self.check_balanced()?;
+        let mut scratch = self.scratch();
+        let mut payments: Vec<Transaction> = Vec::new();
+        let (debtors, creditors) = scratch.debtor_and_creditor_keys();
+        for debtor in debtors.iter().filter(|d| *d != creditor) {
+            let amount = scratch.map[debtor].abs();
+            payments.push(scratch.record_payment(debtor, creditor, amount));
+        }
+        for other in creditors.iter().filter(|c| *c != creditor) {
+            let amount = scratch.map[other].clone();
+            payments.push(scratch.record_payment(creditor, other, amount));
+        }
+        scratch.panic_unless_empty();
+        Ok(payments)
+    }
+
+    /// Settles the ledger through a hub, such as a treasurer who collects and pays out for a group,
+    /// like `settle_to_creditor`. Every debtor pays the hub and the hub pays every creditor, which
+    /// takes more payments than `settle` but matches how such groups work. The hub doesn't need a
+    /// balance of its own, or to be in the ledger at all. Returns an error if the balances don't
+    /// add up to zero.
+    pub fn settle_via_hub(&self, hub: &str) -> Result<Vec<Transaction>, SettleError> {
+        self.check_balanced()?;
+        let mut scratch = self.scratch();
+        if !scratch.map.contains_key(hub) {
+            let currency = self.currency.unwrap_or_else(|| Currency::get(Iso::USD));
+            scratch
+                .map
+                .insert(hub.to_string(), Money::from_major(0, currency));
+        }
+        scratch.settle_to_creditor(hub)
+    }
+
+    /// Settles the ledger so that every party except the reserve pays or receives whole amounts.
+    ///
+    /// Each other party's balance is rounded to whole units before settling, and the difference
+    /// is moved onto the reserve party's balance, which is created if it doesn't exist yet. The
+    /// reserve's payments therefore carry all of the rounding, and their total reflects it. The
+    /// rounding is worked out on a copy, so the ledger is left unchanged.
+    pub fn settle_with_reserve(&self, reserve: &str) -> Result<Vec<Transaction>, SettleError> {
+        self.check_balanced()?;
+        let mut scratch = self.scratch();
+        let mut adjustment: Option<Money> = None;
+        for (party, balance) in scratch.map.iter_mut() {
+            if party != reserve {
+                let rounded = balance.round(0, Round::HalfEven);
+                let difference = balance.clone() - rounded.clone();
+                adjustment = Some(match adjustment {
+                    Some(total) => total + difference,
+                    None => difference,
+                });
+                *balance = rounded;
+            }
+        }
+        if let Some(adjustment) = adjustment {
+            let currency = adjustment.currency();
+            *scratch
+                .map
+                .entry(reserve.to_string())
+                .or_insert_with(|| Money::from_major(0, currency)) += adjustment;
+        }
+        scratch.consume_settle()
+    }
+
+    /// Settles the ledger like `settle`, but first sweeps away every balance smaller than the
+    /// threshold, such as one-cent leftovers from weighted splits, so they don't turn into
+    /// payments of their own. Swept balances are moved onto the party with the largest balance
+    /// (ties go to the earlier name), which keeps the ledger zero-sum.
+    ///
+    /// This trades fairness for fewer payments: each swept party pays or receives up to
+    /// `threshold` less than they should, and the party absorbing them makes up the difference.
+    /// Returns an error if the threshold isn't a positive amount in the ledger's currency, or if
+    /// the balances don't add up to zero.
+    pub fn settle_ignoring_below(&self, threshold: Money) -> Result<Vec<Transaction>, SettleError> {
+        let same_currency = self.currency.is_none_or(|c| c == threshold.currency());
+        if !threshold.is_positive() || !same_currency {
+            return Err(SettleError::InvalidThreshold { threshold });
+        }
+        self.check_balanced()?;
+        let is_small = |balance: &Money| balance.abs().amount() < threshold.amount();
+        let absorber = self
+            .to_vector()
+            .into_iter()
+            .filter(|(_, balance)| !is_small(balance))
+            .max_by(|a, b| {
+                a.1.abs()
+                    .amount()
+                    .cmp(b.1.abs().amount())
+                    .then_with(|| b.0.cmp(&a.0))
+            })
+            .map(|(party, _)| party);
+        let mut scratch = self.scratch();
+        let mut swept = Money::from_major(0, threshold.currency());
+        for balance in scratch.map.values_mut() {
+            if is_small(balance) {
+                swept += balance.clone();
+                *balance = Money::from_major(0, threshold.currency());
+            }
+        }
+        if let Some(party) = absorber {
+            *scratch.map.get_mut(&party).unwrap() += swept;
+        }
+        scratch.consume_settle()
+    }
+
+    /// Settles the ledger by having every debtor pay every creditor in proportion to that
+    /// creditor's share of the total credit.
+    ///
+    /// This is predictable and spreads each debt evenly, but usually needs far more payments than
+    /// `settle`, up to one for every debtor and creditor pair. Shares that don't divide evenly are
+    /// rounded to the smallest unit used by the balances, and the leftover units go to the
+    /// creditors with the largest remainders, so every balance still ends at exactly zero.
+    pub fn settle_proportional(&self) -> Result<Vec<Transaction>, SettleError> {
+        self.check_balanced()?;
+        let mut scratch = self.scratch();
+        let mut payments: Vec<Transaction> = Vec::new();
+        let (debtors, creditors) = self.debtor_and_creditor_keys();
+        let scale = self
+            .map
+            .values()
+            .map(|v| v.amount().scale())
+            .max()
+            .unwrap_or(0);
+        let mut credits: Vec<i128> = creditors
+            .iter()
+            .map(|c| to_units(&self.map[c], scale))
+            .collect();
+
+        for debtor in &debtors {
+            let currency = self.map[debtor].currency();
+            let debt = -to_units(&self.map[debtor], scale);
+            let total_credit: i128 = credits.iter().sum();
+            if total_credit == 0 {
+                break;
+            }
+
+            let mut shares: Vec<i128> = credits.iter().map(|c| debt * c / total_credit).collect();
+            let mut leftover = debt - shares.iter().sum::<i128>();
+            let mut by_remainder: Vec<usize> = (0..credits.len()).collect();
+            by_remainder.sort_by_key(|&i| cmp::Reverse(debt * credits[i] % total_credit));
+            for i in by_remainder {
+                if leftover == 0 {
+                    break;
+                }
+                shares[i] += 1;
+                leftover -= 1;
+            }
+
+            for (i, creditor) in creditors.iter().enumerate() {
+                if shares[i] > 0 {
+                    credits[i] -= shares[i];
+                    let amount = from_units(shares[i], scale, currency);
+                    *scratch.map.get_mut(debtor).unwrap() += amount.clone();
+                    *scratch.map.get_mut(creditor).unwrap() -= amount.clone();
+                    payments
+                        .push(Transaction::new(debtor.clone(), creditor.clone(), amount).unwrap());
+                }
+            }
+        }
+        scratch.panic_unless_empty();
+        Ok(payments)
+    }
+
+    /// Finds the smallest possible set of transactions that will resolve all debts, given a group size.
+    /// This ranges between n/2 (best case) and n-1 (worst case), where n is the number of
+    /// debtors and creditors. Returns an error if the balances don't add up to zero.
+    pub fn settle_upto(&self, group_size: usize) -> Result<Vec<Transaction>, SettleError> {
+        self.scratch().clear_upto(group_size)
+    }
+
+    // Settles the ledger like `settle_upto`, clearing the balances as it goes.
+    fn clear_upto(&mut self, group_size: usize) -> Result<Vec<Transaction>, SettleError> {
+        self.check_balanced()?;
+        let mut payments: Vec<Transaction> = Vec::new();
+        if group_size > 0 {
+            for x in 1..=group_size {
+                payments.append(&mut self.settle_combinations(x));
+            }
+        }
+        payments.append(&mut self.clear_all_entries());
+        Ok(payments)
+    }
+
+    /// Settles the ledger with the fewest payments possible.
+    ///
+    /// Every group of balances that adds up to zero can be settled on its own with one payment
+    /// fewer than its size, so the minimum number of payments is the number of parties with a
+    /// balance minus the largest number of disjoint zero-sum groups they can be split into. This
+    /// searches every subset of the parties to find that split, so it returns an error for ledgers
+    /// with more than `MAX_OPTIMAL_PARTIES` parties with a balance, or whose balances don't add up
+    /// to zero.
+    pub fn settle_optimal(&self) -> Result<Vec<Transaction>, SettleError> {
+        let ZeroSumGroups {
+            entries,
+            sums,
+            groups,
+        } = self.zero_sum_groups()?;
+        let size = groups.len();
+        let mut scratch = self.scratch();
+
+        // Walk back from the full set, one party at a time, along a path that keeps the most
+        // groups. Each time the remaining parties add up to zero, the parties removed since the
+        // last time form one group.
+        let mut payments: Vec<Transaction> = Vec::new();
+        let mut mask = size - 1;
+        let mut group_end = mask;
+        while mask != 0 {
+            let i = (0..entries.len())
+                .find(|&i| {
+                    mask & (1 << i) != 0
+                        && groups[mask ^ (1 << i)] + usize::from(sums[mask] == 0) == groups[mask]
+                })
+                .unwrap();
+            mask ^= 1 << i;
+            if sums[mask] == 0 {
+                payments.append(&mut scratch.settle_group(&entries, group_end ^ mask));
+                group_end = mask;
+            }
+        }
+        Ok(payments)
+    }
+
+    /// Returns the fewest payments that can settle the ledger, without settling it: the number of
+    /// parties with a balance, minus the most disjoint zero-sum groups they can be split into.
+    /// This is how many payments `settle_optimal` makes, and a lower bound for every other
+    /// settlement. Like `settle_optimal`, it returns an error for ledgers with more than
+    /// `MAX_OPTIMAL_PARTIES` parties with a balance, or whose balances don't add up to zero.
+    pub fn min_transactions(&self) -> Result<usize, SettleError> {
+        let search = self.zero_sum_groups()?;
+        Ok(search.entries.len() - search.groups[search.groups.len() - 1])
+    }
+
+    /// Settles the ledger by repeatedly having the largest remaining debtor pay the largest
+    /// remaining creditor. This takes O(n log n) time and never needs more than n-1 payments,
+    /// where n is the number of parties with a balance, so it scales to thousands of parties.
+    ///
+    /// It trades optimality for speed: unlike `settle`, it doesn't search for groups of balances
+    /// that cancel out, so it can use more payments than necessary. Prefer `settle` for small
+    /// groups. Returns an error if the balances don't add up to zero.
+    pub fn settle_greedy(&self) -> Result<Vec<Transaction>, SettleError> {
+        self.check_balanced()?;
+        let mut debtors: BinaryHeap<(Decimal, String)> = BinaryHeap::new();
+        let mut creditors: BinaryHeap<(Decimal, String)> = BinaryHeap::new();
+        for (party, balance) in &self.map {
+            if balance.is_negative() {
+                debtors.push((-*balance.amount(), party.clone()));
+            } else if balance.is_positive() {
+                creditors.push((*balance.amount(), party.clone()));
+            }
+        }
+
+        let mut scratch = self.scratch();
+        let mut payments: Vec<Transaction> = Vec::new();
+        while let (Some((debt, debtor)), Some((credit, creditor))) =
+            (debtors.pop(), creditors.pop())
+        {
+            let amount = cmp::min(debt, credit);
+            let currency = self.map[&debtor].currency();
+            payments.push(scratch.record_payment(
+                &debtor,
+                &creditor,
+                Money::from_decimal(amount, currency),
+            ));
+            if debt > amount {
+                debtors.push((debt - amount, debtor));
+            }
+            if credit > amount {
+                creditors.push((credit - amount, creditor));
+            }
+        }
+        Ok(payments)
+    }
+
+    /// Returns true if settling with the combination search (`settle`) and greedily
+    /// (`settle_greedy`) take the same number of payments. Both run on copies, so the ledger is
+    /// left unchanged.
+    pub fn strategies_agree(&self) -> Result<bool, SettleError> {
+        let searched = self.settle()?.len();
+        let greedy = self.settle_greedy()?.len();
+        Ok(searched == greedy)
+    }
+
+    /// Returns how many fewer payments settling the ledger takes than paying back every recorded
+    /// debt individually. Each settleable transaction in the history counts as one payment, and
+    /// each multi-party transaction as one payment from every debtor to every other creditor.
+    pub fn simplification_savings(&self) -> Result<usize, SettleError> {
+        let single = self.history.iter().filter(|t| t.settleable).count();
+        let multi: usize = self
+            .multi_party_history
+            .iter()
+            .map(|t| {
+                t.debtors
+                    .iter()
+                    .cartesian_product(&t.creditors)
+                    .filter(|(debtor, creditor)| debtor != creditor)
+                    .count()
+            })
+            .sum();
+        Ok((single + multi).saturating_sub(self.settle()?.len()))
+    }
+
+    /// Returns the number of combinations that `settle_upto` would examine for a given group size,
+    /// which is the sum of C(n, k) for k in 1..=group_size. Saturates at u128::MAX.
+    pub fn settlement_search_cost(&self, group_size: usize) -> u128 {
+        let n = self.map.len() as u128;
+        let mut combinations: u128 = 1;
+        let mut total: u128 = 0;
+        for k in 1..=(group_size as u128).min(n) {
+            combinations = match combinations.checked_mul(n - k + 1) {
+                Some(c) => c / k,
+                None => return u128::MAX,
+            };
+            total = total.saturating_add(combinations);
+        }
+        total
+    }
+
+    /// Returns the party's net balance, which is negative if they owe money and positive if they
+    /// are owed money. Returns zero for a party that isn't in the ledger, in the ledger's currency,
+    /// or in USD if no transactions have been added yet.
+    pub fn balance_of(&self, party: &str) -> Money {
+        match self.map.get(party) {
+            Some(balance) => balance.clone(),
+            None => Money::from_major(0, self.currency.unwrap_or_else(|| Currency::get(Iso::USD))),
+        }
+    }
+
+    /// Returns true if the balances add up to zero, which they must for the ledger to settle.
+    pub fn is_balanced(&self) -> bool {
+        self.check_balanced().is_ok()
+    }
+
+    /// Returns the total owed by every debtor, as a positive amount, i.e. the money that will
+    /// change hands to settle the ledger. The currency is chosen as in `balance_of`.
+    pub fn total_debt(&self) -> Money {
+        let zero = Money::from_major(0, self.currency.unwrap_or_else(|| Currency::get(Iso::USD)));
+        self.map
+            .values()
+            .filter(|balance| balance.is_negative())
+            .fold(zero, |total, balance| total + balance.abs())
+    }
+
+    /// Returns every party tracked in the ledger, sorted by name.
+    pub fn parties(&self) -> Vec<&str> {
+        let mut parties: Vec<&str> = self.map.keys().map(String::as_str).collect();
+        parties.sort_unstable();
+        parties
+    }
+
+    /// Returns the number of parties with an entry in the ledger, including any whose balance has
+    /// returned to zero.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if no party has an entry in the ledger.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Resets the ledger so it can be reused, e.g. for the next trip, keeping the memory it has
+    /// already allocated. Balances, history and metadata are removed, the currency is forgotten
+    /// and transaction ids start again from "1". A seed given to `with_seed` is kept.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.currency = None;
+        self.history.clear();
+        self.multi_party_history.clear();
+        self.metadata.clear();
+        self.assigned_ids = 0;
+    }
+
+    /// Returns true if the party already has an entry in the ledger. Adding a transaction for a
+    /// party that isn't known will create a new entry for them.
+    pub fn is_known_party(&self, party: &str) -> bool {
+        self.map.contains_key(party)
+    }
+
+    /// Returns a new ledger holding only the balances of the named parties, e.g. to export one
+    /// user's data. Unknown parties are ignored, and the history is not carried over.
+    ///
+    /// The subset is usually not zero-sum, so it may not settle cleanly on its own.
+    pub fn subset(&self, parties: &[String]) -> Ledger {
+        let mut ledger = Ledger::new();
+        ledger.currency = self.currency;
+        for party in parties {
+            if let Some(balance) = self.map.get(party) {
+                ledger.map.insert(party.clone(), balance.clone());
+            }
+        }
+        ledger
+    }
+
+    /// Adds another ledger into this one, e.g. to roll several trips into a grand total. Balances
+    /// are summed party by party, and the other ledger's history is appended to this one's.
+    /// Transactions the other ledger numbered itself are renumbered to follow on from this
+    /// ledger's ids, so ids stay unique, while ids that were given explicitly are kept. Metadata
+    /// already stored in this ledger takes precedence.
+    ///
+    /// Returns an error, leaving this ledger unchanged, if the ledgers are in different currencies.
+    pub fn merge(&mut self, other: Ledger) -> Result<(), TransactionError> {
+        if let Some(currency) = other.currency {
+            self.check_currency(currency)?;
+        }
+        for (party, balance) in other.map {
+            let currency = balance.currency();
+            self.adjust_balance(party, balance, currency);
+        }
+        let other_ids = 1..=other.assigned_ids;
+        for mut transaction in other.history {
+            let numbered = transaction
+                .id
+                .as_deref()
+                .and_then(|id| id.parse::<u64>().ok())
+                .is_some_and(|n| other_ids.contains(&n));
+            if numbered {
+                self.assigned_ids += 1;
+                transaction.id = Some(self.assigned_ids.to_string());
+            }
+            self.history.push(transaction);
+        }
+        self.multi_party_history.extend(other.multi_party_history);
+        for (key, value) in other.metadata {
+            self.metadata.entry(key).or_insert(value);
+        }
+        Ok(())
+    }
+
+    /// Returns a new ledger combining the two ledgers, as with `merge`.
+    pub fn merged(mut first: Ledger, second: Ledger) -> Result<Ledger, TransactionError> {
+        first.merge(second)?;
+        Ok(first)
+    }
+
+    /// Stores a piece of metadata (e.g. a trip name or owner) alongside the ledger, replacing any
+    /// previous value for the key. Metadata never affects balances or settlement.
+    pub fn set_metadata(&mut self, key: String, value: String) {
+        self.metadata.insert(key, value);
+    }
+
+    /// Returns the metadata stored for a key, if any.
+    pub fn get_metadata(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(String::as_str)
+    }
+
+    /// Returns every transaction that has been added to the ledger, in the order it was added.
+    pub fn history(&self) -> &[Transaction] {
+        &self.history
+    }
+
+    /// Returns every multi-party transaction that has been added to the ledger, in the order it
+    /// was added.
+    pub fn multi_party_history(&self) -> &[MultiPartyTransaction] {
+        &self.multi_party_history
+    }
+
+    /// Returns how many transactions, including multi-party transactions, are in the history.
+    pub fn transaction_count(&self) -> usize {
+        self.history.len() + self.multi_party_history.len()
+    }
+
+    /// Returns the balances of every party as they stood on the given date, by replaying the
+    /// transactions in the history that took place on or before it. Transactions without a date,
+    /// including all multi-party transactions, are always included.
+    #[cfg(feature = "chrono")]
+    pub fn balances_as_of(&self, date: NaiveDate) -> HashMap<String, Money> {
+        let mut ledger = Ledger::new();
+        for transaction in &self.history {
+            if transaction.date.is_none_or(|d| d <= date) {
+                ledger.apply_transaction(transaction);
+            }
+        }
+        for transaction in &self.multi_party_history {
+            ledger.apply_multi_party_transaction(transaction);
+        }
+        ledger.map
+    }
+
+    /// Returns the smallest set of payments that settles only the transactions whose timestamp
+    /// falls between start and end, inclusive. Transactions outside the window or without a
+    /// timestamp, including all multi-party transactions, are left out. Like `settle`, the ledger
+    /// is left unchanged, so the same window always gives the same payments; pass them to `apply`
+    /// to record that they've been made.
+    #[cfg(feature = "chrono")]
+    pub fn settle_between(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Vec<Transaction>, SettleError> {
+        let mut window = Ledger {
+            seed: self.seed,
+            ..Ledger::new()
+        };
+        for transaction in &self.history {
+            if transaction
+                .timestamp
+                .is_some_and(|t| start <= t && t <= end)
+            {
+                window.apply_transaction(transaction);
+            }
+        }
+        window.consume_settle()
+    }
+
+    /// Returns how far each party's balance has drifted from an exact split, since multi-party
+    /// transactions are allocated in whole minor units. A positive drift means the party's balance is
+    /// higher than an exact split would have left it.
+    pub fn rounding_drift(&self) -> HashMap<String, Money> {
+        let mut drift: HashMap<String, Money> = HashMap::new();
+        for transaction in &self.multi_party_history {
+            let currency = transaction.amount.currency();
+            let exact_share = |weight: i32, weights: &[i32]| {
+                let total: i64 = weights.iter().map(|&w| i64::from(w)).sum();
+                transaction.amount.clone() * Decimal::from(weight) / Decimal::from(total)
+            };
+
+            let debts = transaction.debtor_shares();
+            for ((debtor, share), weight) in debts.into_iter().zip(&transaction.debtor_weights) {
+                let exact = exact_share(*weight, &transaction.debtor_weights);
+                *drift
+                    .entry(debtor)
+                    .or_insert_with(|| Money::from_major(0, currency)) += exact - share;
+            }
+
+            let credits = transaction.creditor_shares();
+            for ((creditor, share), weight) in
+                credits.into_iter().zip(&transaction.creditor_weights)
+            {
+                let exact = exact_share(*weight, &transaction.creditor_weights);
+                *drift
+                    .entry(creditor)
+                    .or_insert_with(|| Money::from_major(0, currency)) += share - exact;
+            }
+        }
+        drift
+    }
+
+    /// Returns, for each party, the total they were owed as a creditor and the total they owed as
+    /// a debtor across the whole history, as `(total as creditor, total as debtor)`. Multi-party
+    /// transactions count each party's share. Unlike the balances, the totals are not netted.
+    pub fn activity_summary(&self) -> HashMap<String, (Money, Money)> {
+        let mut summary: HashMap<String, (Money, Money)> = HashMap::new();
+        let mut record = |party: &str, amount: &Money, as_creditor: bool| {
+            let zero = Money::from_major(0, amount.currency());
+            let totals = summary
+                .entry(party.to_string())
+                .or_insert_with(|| (zero.clone(), zero));
+            if as_creditor {
+                totals.0 += amount.clone();
+            } else {
+                totals.1 += amount.clone();
+            }
+        };
+
+        for transaction in &self.history {
+            record(&transaction.creditor, &transaction.amount, true);
+            record(&transaction.debtor, &transaction.amount, false);
+        }
+        for transaction in &self.multi_party_history {
+            for (creditor, share) in transaction.creditor_shares() {
+                record(&creditor, &share, true);
+            }
+            for (debtor, share) in transaction.debtor_shares() {
+                record(&debtor, &share, false);
+            }
+        }
+        summary
+    }
+
+    /// Returns the gross amount `from` has owed `to` across the whole history, without netting it
+    /// against anything `to` owed `from`, e.g. how much Alice has ever owed Bob for his expenses.
+    /// For a multi-party transaction, `from`'s share is split between the creditors by weight, and
+    /// `to`'s part of it is counted without rounding. It is worked out from the history when
+    /// called, so it costs no extra memory.
+    pub fn flow_between(&self, from: &str, to: &str) -> Money {
+        let currency = self.currency.unwrap_or_else(|| Currency::get(Iso::USD));
+        let mut flow = Money::from_major(0, currency);
+        for transaction in &self.history {
+            if transaction.debtor == from && transaction.creditor == to {
+                flow += transaction.amount.clone();
+            }
+        }
+        for transaction in &self.multi_party_history {
+            let weight = match transaction.creditors.iter().position(|c| c == to) {
+                Some(i) => Decimal::from(transaction.creditor_weights[i]),
+                None => continue,
+            };
+            let total_weight: i64 = transaction
+                .creditor_weights
+                .iter()
+                .map(|&w| i64::from(w))
+                .sum();
+            for (debtor, share) in transaction.debtor_shares() {
+                if debtor == from {
+                    flow += share * weight / Decimal::from(total_weight);
+                }
+            }
+        }
+        flow
+    }
+
+    /// Iterates over every party and their balance without copying them, in no particular order.
+    /// Use `to_vector` for an owned list sorted by party.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Money)> {
+        self.map
+            .iter()
+            .map(|(party, balance)| (party.as_str(), balance))
+    }
+
+    // Converts the ledger from a hashmap into a set of vector-tuples containing the
+    // debtor/creditor and the amount, sorted by party. Debts are negative, and credits are
+    // positive.
+    pub fn to_vector(&self) -> Vec<(String, Money)> {
+        let mut ledger_entries: Vec<(String, Money)> = Vec::new();
+
+        for (key, val) in self.map.iter() {
+            ledger_entries.push((key.clone(), val.clone()));
+        }
+        ledger_entries.sort_by(|a, b| a.0.cmp(&b.0));
+        ledger_entries
+    }
+
+    // Returns an error if the currency isn't the ledger's currency. The first currency checked
+    // becomes the ledger's currency.
+    fn check_currency(&mut self, currency: &'static Currency) -> Result<(), CurrencyMismatchError> {
+        match self.currency {
+            Some(expected) if expected != currency => Err(CurrencyMismatchError {
+                expected,
+                found: currency,
+            }),
+            _ => {
+                self.currency = Some(currency);
+                Ok(())
+            }
+        }
+    }
+
+    // Updates the debtor and creditor balances for a transaction, without recording it in the
+    // history. Transactions that aren't settleable leave the balances unchanged.
+    fn apply_transaction(&mut self, transaction: &Transaction) {
+        if !transaction.settleable {
+            return;
+        }
+        let currency = transaction.amount.currency();
+        *self
+            .map
+            .entry(transaction.debtor.clone())
+            .or_insert_with(|| Money::from_major(0, currency)) -= transaction.amount.clone();
+        *self
+            .map
+            .entry(transaction.creditor.clone())
+            .or_insert_with(|| Money::from_major(0, currency)) += transaction.amount.clone();
+    }
+
+    // Updates the balances of every debtor and creditor in a multi-party transaction, without
+    // recording it in the history.
+    fn apply_multi_party_transaction(&mut self, transaction: &MultiPartyTransaction) {
+        let currency = transaction.amount.currency();
+        for (debtor, share) in transaction.debtor_shares() {
+            *self
+                .map
+                .entry(debtor)
+                .or_insert_with(|| Money::from_major(0, currency)) -= share;
+        }
+        for (creditor, share) in transaction.creditor_shares() {
+            *self
+                .map
+                .entry(creditor)
+                .or_insert_with(|| Money::from_major(0, currency)) += share;
+        }
+    }
+
+    // Reverses the debtor and creditor balance updates for a transaction, without touching the
+    // history.
+    fn unapply_transaction(&mut self, transaction: &Transaction) {
+        if !transaction.settleable {
+            return;
+        }
+        let currency = transaction.amount.currency();
+        self.adjust_balance(
+            transaction.debtor.clone(),
+            transaction.amount.clone(),
+            currency,
+        );
+        self.adjust_balance(
+            transaction.creditor.clone(),
+            transaction.amount.negated(),
+            currency,
+        );
+    }
+
+    // Adds an amount to a party's balance, creating the entry at zero if needed and removing it
+    // if the balance returns to exactly zero.
+    fn adjust_balance(&mut self, party: String, amount: Money, currency: &'static Currency) {
+        let balance = self
+            .map
+            .entry(party.clone())
+            .or_insert_with(|| Money::from_major(0, currency));
+        *balance += amount;
+        if balance.is_zero() {
+            self.map.remove(&party);
+        }
+    }
+
+    // Searches every subset of the parties with a balance for the most disjoint zero-sum groups
+    // they can be split into, for `settle_optimal` and `min_transactions`.
+    fn zero_sum_groups(&self) -> Result<ZeroSumGroups, SettleError> {
+        self.check_balanced()?;
+        let entries: Vec<(String, Money)> = self
+            .to_vector()
+            .into_iter()
+            .filter(|(_, balance)| !balance.is_zero())
+            .collect();
+        if entries.len() > MAX_OPTIMAL_PARTIES {
+            return Err(SettleError::TooManyParties {
+                parties: entries.len(),
+                limit: MAX_OPTIMAL_PARTIES,
+            });
+        }
+
+        let scale = entries
+            .iter()
+            .map(|(_, b)| b.amount().scale())
+            .max()
+            .unwrap_or(0);
+        let size = 1usize << entries.len();
+        let mut sums: Vec<i128> = vec![0; size];
+        let mut groups: Vec<usize> = vec![0; size];
+        for mask in 1..size {
+            let lowest = mask.trailing_zeros() as usize;
+            sums[mask] = sums[mask & (mask - 1)] + to_units(&entries[lowest].1, scale);
+            let best = (0..entries.len())
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| groups[mask ^ (1 << i)])
+                .max()
+                .unwrap();
+            groups[mask] = best + usize::from(sums[mask] == 0);
+        }
+        Ok(ZeroSumGroups {
+            entries,
+            sums,
+            groups,
+        })
+    }
+
+    // Returns a copy of the balances, currency and seed to settle on, so that settling leaves the
+    // ledger itself unchanged.
+    fn scratch(&self) -> Ledger {
+        Ledger {
+            map: self.map.clone(),
+            currency: self.currency,
+            seed: self.seed,
+            ..Ledger::default()
+        }
+    }
+
+    // Records a payment from debtor to creditor against their balances, and returns it.
+    fn record_payment(&mut self, debtor: &str, creditor: &str, amount: Money) -> Transaction {
+        *self.map.get_mut(debtor).unwrap() += amount.clone();
+        *self.map.get_mut(creditor).unwrap() -= amount.clone();
+        Transaction::new(debtor.to_string(), creditor.to_string(), amount).unwrap()
+    }
+
+    // Returns an error unless the balances add up to zero, which every settlement relies on.
+    fn check_balanced(&self) -> Result<(), SettleError> {
+        let mut balances = self.map.values();
+        let first = match balances.next() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+        let total = balances.fold(*first.amount(), |total, balance| total + balance.amount());
+        if total.is_zero() {
+            return Ok(());
+        }
+        Err(SettleError::Unbalanced {
+            residual: Money::from_decimal(total, first.currency()),
+        })
+    }
+
+    fn panic_unless_empty(&self) {
+        for (_, val) in self.map.iter() {
+            if !val.is_zero() {
+                panic!();
+            }
+        }
+    }
+
+    // Settles combinations of a specified size. A combination is a set of ledger balances that
+    // are zero sum (add up to zero).
+    // e.g.  A = 3, B = -2 and C= -1 is a group entry of 3, since the three of them settle to 0.
+    fn settle_combinations(&mut self, combo_size: usize) -> Vec<Transaction> {
+        let mut payments: Vec<Transaction> = Vec::new();
+        let settling_combinations = self.find_zero_sum_combinations(combo_size);
+
+        for combo in settling_combinations {
+            let mut debtor_keys: Vec<String> = Vec::new();
+            let mut creditor_keys: Vec<String> = Vec::new();
+            for item in combo {
+                if item.1.is_positive() {
+                    creditor_keys.push(item.0)
+                } else if item.1.is_negative() {
+                    debtor_keys.push(item.0)
+                }
+            }
+            payments.append(&mut self.clear_given_keys(debtor_keys, creditor_keys));
+        }
+        payments
+    }
+
+    // Settles all entries left in the ledger with a balance, in order of their names.
+    fn clear_all_entries(&mut self) -> Vec<Transaction> {
+        let (debtor_keys, creditor_keys) = self.debtor_and_creditor_keys();
+        let transactions = self.clear_given_keys(debtor_keys, creditor_keys);
+        self.panic_unless_empty();
+        transactions
+    }
+
+    // Settles the entries whose bits are set in the mask against each other.
+    fn settle_group(&mut self, entries: &[(String, Money)], mask: usize) -> Vec<Transaction> {
+        let (mut debtors, mut creditors) = (Vec::new(), Vec::new());
+        for (i, (party, balance)) in entries.iter().enumerate() {
+            if mask & (1 << i) == 0 {
+                continue;
+            }
+            if balance.is_negative() {
+                debtors.push(party.clone());
+            } else {
+                creditors.push(party.clone());
+            }
+        }
+        self.clear_given_keys(debtors, creditors)
+    }
+
+    // Settles a specified list of debtors and creditors, in the order given.
+    fn clear_given_keys(
+        &mut self,
+        debtors: Vec<String>,
+        creditors: Vec<String>,
+    ) -> Vec<Transaction> {
+        let mut payments: Vec<Transaction> = Vec::new();
+
+        for debtor in &debtors {
+            let mut debtor_amount = self.map.get(debtor).unwrap().clone();
+
+            for creditor in &creditors {
+                let mut creditor_amount = self.map.get(creditor).unwrap().clone();
+
+                // If there's still debt and credit, create a payment.
+                // If either one is missing, try grabbing another creditor
+                // If you run out of creditors, grab another debtor and start again.
+                while (creditor_amount.is_positive()) && (debtor_amount.is_negative()) {
+                    let debt = debtor_amount.abs();
+                    let payment_amount = if creditor_amount.amount() < debt.amount() {
+                        creditor_amount.clone()
+                    } else {
+                        debt
+                    };
+
+                    debtor_amount += payment_amount.clone();
+                    self.map.insert(debtor.clone(), debtor_amount.clone());
+
+                    creditor_amount -= payment_amount.clone();
+                    self.map.insert(creditor.clone(), creditor_amount.clone());
+
+                    payments.push(
+                        Transaction::new(debtor.clone(), creditor.clone(), payment_amount).unwrap(),
+                    );
+                }
+            }
+        }
+        payments
+    }
+
+    // Finds zero sum combinations of a given size of ledger entries.
+    fn find_zero_sum_combinations(&self, combo_size: usize) -> Vec<Vec<(String, Money)>> {
+        let mut zero_sum_combinations: Vec<Vec<(String, Money)>> = Vec::new();
+        let combinations = self.to_vector().into_iter().combinations(combo_size);
         for item in combinations {
-            if item
+            let zero = Money::from_major(0, item[0].1.currency());
+            if item.iter().fold(zero, |acc, x| acc + x.1.clone()).is_zero() {
+                zero_sum_combinations.push(item);
+            }
+        }
+        zero_sum_combinations
+    }
+
+    // Returns vectors of keys of debtors and creditors with an active balance, sorted by name so
+    // that settlements are the same every time, then shuffled by the seed if the ledger has one.
+    fn debtor_and_creditor_keys(&self) -> (Vec<String>, Vec<String>) {
+        let mut creditors: Vec<String> = Vec::new();
+        let mut debtors: Vec<String> = Vec::new();
+
+        for (person, value) in &self.map {
+            if value.is_positive() {
+                creditors.push(person.clone());
+            } else if value.is_negative() {
+                debtors.push(person.clone());
+            }
+        }
+        debtors.sort();
+        creditors.sort();
+        if let Some(seed) = self.seed {
+            let mut rng = StdRng::seed_from_u64(seed);
+            debtors.shuffle(&mut rng);
+            creditors.shuffle(&mut rng);
+        }
+        (debtors, creditors)
+    }
+}
+
+/// Represents a recurring tab, such as monthly rent between roommates, which is settled and
+/// reset at the end of every period.
+///
+/// Payments that weren't made by the end of a period can be carried over into the next one. Each
+/// carried over payment is added as a debt from its debtor to its creditor, so the new period's
+/// ledger still sums to zero.
+#[derive(Debug, Default)]
+pub struct Tab {
+    ledger: Ledger,
+    period: usize,
+}
+
+impl Tab {
+    /// Creates a new Tab, starting in period 0.
+    pub fn new() -> Tab {
+        Tab {
+            ledger: Ledger::new(),
+            period: 0,
+        }
+    }
+
+    /// Accepts a transaction and adds it to the current period.
+    pub fn add_transaction(&mut self, transaction: Transaction) -> Result<(), TransactionError> {
+        self.ledger.add_transaction(transaction)
+    }
+
+    /// Accepts a multi-party transaction and adds it to the current period.
+    pub fn add_multi_party_transaction(
+        &mut self,
+        transaction: MultiPartyTransaction,
+    ) -> Result<(), TransactionError> {
+        self.ledger.add_multi_party_transaction(transaction)
+    }
+
+    /// Returns the ledger for the current period.
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    /// Returns the number of periods that have been closed so far.
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    /// Settles the current period, returning the payments needed, and starts a new empty period.
+    /// If the period can't be settled, it is left open and the error is returned.
+    pub fn close_period(&mut self) -> Result<Vec<Transaction>, SettleError> {
+        let payments = self.ledger.settle()?;
+        self.ledger = Ledger::new();
+        self.period += 1;
+        Ok(payments)
+    }
+
+    /// Carries payments from a closed period that were never made over into the current period.
+    pub fn carry_over(&mut self, unpaid: Vec<Transaction>) -> Result<(), TransactionError> {
+        self.ledger.add_transactions(unpaid)
+    }
+}
+
+// Returns an amount as a whole number of units of 10^-scale.
+fn to_units(money: &Money, scale: u32) -> i128 {
+    let mut amount = *money.amount();
+    amount.rescale(scale);
+    amount.mantissa()
+}
+
+// Returns a whole number of units of 10^-scale as an amount of the currency.
+fn from_units(units: i128, scale: u32, currency: &'static Currency) -> Money {
+    Money::from_decimal(Decimal::from_i128_with_scale(units, scale), currency)
+}
+
+// The result of searching a ledger's parties for zero-sum groups. sums[mask] is the total balance
+// of the entries whose bits are set in mask, and groups[mask] is the most disjoint zero-sum groups
+// those entries can be split into.
+struct ZeroSumGroups {
+    entries: Vec<(String, Money)>,
+    sums: Vec<i128>,
+    groups: Vec<usize>,
+}
+
+// Splits every payment larger than max into payments of max between the same parties, followed
+// by one for the remainder.
+fn split_payments(payments: Vec<Transaction>, max: &Money) -> Vec<Transaction> {
+    let mut split: Vec<Transaction> = Vec::new();
+    for payment in payments {
+        let mut remaining = payment.amount;
+        while remaining.amount() > max.amount() {
+            remaining -= max.clone();
+            split.push(
+                Transaction::new(
+                    payment.debtor.clone(),
+                    payment.creditor.clone(),
+                    max.clone(),
+                )
+                .unwrap(),
+            );
+        }
+        split.push(Transaction::new(payment.debtor, payment.creditor, remaining).unwrap());
+    }
+    split
+}
+
+/// Indexes a set of settlement payments by debtor, then creditor, for quick lookups of how much
+/// one party pays another. Payments between the same pair of parties are summed.
+pub fn settlement_map(payments: &[Transaction]) -> HashMap<String, HashMap<String, Money>> {
+    let mut map: HashMap<String, HashMap<String, Money>> = HashMap::new();
+    for payment in payments {
+        let owed = map
+            .entry(payment.debtor.clone())
+            .or_default()
+            .entry(payment.creditor.clone())
+            .or_insert_with(|| Money::from_major(0, payment.amount.currency()));
+        *owed += payment.amount.clone();
+    }
+    map
+}
+
+/// Renders a set of settlement payments as a Graphviz DOT digraph, with an edge from each debtor
+/// to the creditor they pay, labelled with the amount. Names are quoted and escaped, so they can
+/// contain spaces and punctuation.
+pub fn settlement_to_dot(payments: &[Transaction]) -> String {
+    let mut dot = String::from("digraph settlement {\n");
+    for payment in payments {
+        dot.push_str(&format!(
+            "    {} -> {} [label={}];\n",
+            dot_string(&payment.debtor),
+            dot_string(&payment.creditor),
+            dot_string(&payment.amount.format_with_symbol())
+        ));
+    }
+    dot.push('}');
+    dot.push('\n');
+    dot
+}
+
+// Quotes a string for DOT, escaping backslashes and double quotes.
+fn dot_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Combines two sets of settlement payments and re-settles them, returning the smallest set of
+/// payments that moves the same net amounts between parties as both sets together.
+///
+/// Panics if the payments aren't all in the same currency.
+pub fn combine_settlements(a: Vec<Transaction>, b: Vec<Transaction>) -> Vec<Transaction> {
+    let mut ledger = Ledger::new();
+    ledger.add_transactions(a.into_iter().chain(b)).unwrap();
+    // Each settlement nets to zero on its own, so together they always balance.
+    ledger.settle_unchecked()
+}
+
+/// Compares two settlements, returning the payments that were removed and the payments that were
+/// added between them. Payments that appear in both, in any order, are treated as unchanged.
+pub fn settlement_diff(
+    before: &[Transaction],
+    after: &[Transaction],
+) -> (Vec<Transaction>, Vec<Transaction>) {
+    let mut added: Vec<&Transaction> = after.iter().collect();
+    let mut removed: Vec<Transaction> = Vec::new();
+    for payment in before {
+        match added.iter().position(|p| *p == payment) {
+            Some(i) => {
+                added.remove(i);
+            }
+            None => removed.push(payment.clone()),
+        }
+    }
+    (removed, added.into_iter().cloned().collect())
+}
+
+/// Returns the payment with the largest amount, or None if there are no payments.
+pub fn max_payment(payments: &[Transaction]) -> Option<&Transaction> {
+    payments.iter().max_by(|a, b| a.amount.cmp(&b.amount))
+}
+
+/// Settles balances given as whole numbers of cents, using the same combination search as
+/// `Ledger::settle` but without any Money or Decimal arithmetic, and returns each payment as
+/// `(debtor, creditor, cents)`. Negative balances are debts, and positive ones are credits.
+/// Parties are visited in order of their names, as `Ledger::settle` does, so both return the
+/// same payments for the same balances whatever order they're given in. Any i64 balances are
+/// accepted; sums are worked out in i128 so they can't overflow.
+///
+/// Panics if the balances don't add up to zero.
+pub fn settle_cents(balances: &[(String, i64)]) -> Vec<(String, String, i64)> {
+    let mut remaining: Vec<(String, i64)> = balances
+        .iter()
+        .filter(|(_, cents)| *cents != 0)
+        .cloned()
+        .collect();
+    remaining.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut payments: Vec<(String, String, i64)> = Vec::new();
+
+    for combo_size in 1..remaining.len() {
+        let combinations: Vec<Vec<usize>> = (0..remaining.len())
+            .combinations(combo_size)
+            .filter(|combo| {
+                combo
+                    .iter()
+                    .map(|&i| i128::from(remaining[i].1))
+                    .sum::<i128>()
+                    == 0
+            })
+            .collect();
+        for combo in combinations {
+            clear_cents(&mut remaining, &combo, &mut payments);
+        }
+    }
+    let everyone: Vec<usize> = (0..remaining.len()).collect();
+    clear_cents(&mut remaining, &everyone, &mut payments);
+
+    if remaining.iter().any(|(_, cents)| *cents != 0) {
+        panic!("balances must add up to zero");
+    }
+    payments
+}
+
+// Settles the balances at the given indices against each other, recording the payments made.
+fn clear_cents(
+    balances: &mut [(String, i64)],
+    indices: &[usize],
+    payments: &mut Vec<(String, String, i64)>,
+) {
+    for &debtor in indices {
+        for &creditor in indices {
+            // A debt of i64::MIN saturates to i64::MAX, which is still more than any credit.
+            let cents = cmp::min(balances[debtor].1.saturating_neg(), balances[creditor].1);
+            if cents > 0 {
+                balances[debtor].1 += cents;
+                balances[creditor].1 -= cents;
+                payments.push((
+                    balances[debtor].0.clone(),
+                    balances[creditor].0.clone(),
+                    cents,
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use rusty_money::money;
+    use rusty_money::Iso::*;
+
+    // The settlement should always choose credits and debits that are equal over any other type.
+    // This allows two entries in the ledger to be removed in exchange for a single payment.
+    // For example, if A = -10 and B = +10, they should always first over any other possibility
+    #[test]
+    fn ledger_settle_matches_equal_debts_and_credits() {
+        let mut ledger = Ledger::new();
+
+        let expected_results = vec![
+            transaction!("A", "B", (2, "USD")),
+            transaction!("C", "F", (3, "USD")),
+            transaction!("D", "F", (5, "USD")),
+            transaction!("E", "F", (7, "USD")),
+        ];
+
+        // The worst case match (i.e. random) can accidentially find the optimal solution for small
+        // sets, so we repeat to make this very unlikely
+        for _ in 0..5 {
+            ledger
+                .add_transaction(transaction!("A", "B", (2, "USD")))
+                .unwrap();
+            ledger
+                .add_transaction(transaction!("C", "F", (3, "USD")))
+                .unwrap();
+            ledger
+                .add_transaction(transaction!("D", "F", (5, "USD")))
+                .unwrap();
+            ledger
+                .add_transaction(transaction!("E", "F", (7, "USD")))
+                .unwrap();
+            let mut payments = ledger.consume_settle().unwrap();
+            payments.sort();
+            assert_eq!(payments, expected_results);
+        }
+    }
+
+    // Next, the settlement should always choose 3 credits and debits that are zero sum over any other.
+    // This allows three entries in the ledger to be removed in exchange for two payments.
+    // For example, if A = -10,  B = +5, C= +5.
+    #[test]
+    fn ledger_settle_with_size_3_matches_groups_of_3_credits_and_debits() {
+        // Test that group matched  payments are always settled first.
+        let mut ledger = Ledger::new();
+
+        let expected_results = vec![
+            transaction!("A", "D", (3, "USD")),
+            transaction!("C", "D", (4, "USD")),
+            transaction!("E", "B", (10, "USD")),
+            transaction!("F", "B", (17, "USD")),
+            transaction!("J", "K", (20, "USD")),
+            transaction!("U", "K", (21, "USD")),
+        ];
+
+        // The worst case match (i.e. random) can accidentially find the optimal solution for small
+        // sets, so we repeat to make this very unlikely
+        for _ in 0..5 {
+            ledger
+                .add_transaction(transaction!("A", "D", (3, "USD")))
+                .unwrap();
+            ledger
+                .add_transaction(transaction!("C", "D", (4, "USD")))
+                .unwrap();
+            ledger
+                .add_transaction(transaction!("E", "B", (10, "USD")))
+                .unwrap();
+            ledger
+                .add_transaction(transaction!("F", "B", (17, "USD")))
+                .unwrap();
+            ledger
+                .add_transaction(transaction!("J", "K", (20, "USD")))
+                .unwrap();
+            ledger
+                .add_transaction(transaction!("U", "K", (21, "USD")))
+                .unwrap();
+
+            let mut payments = ledger.consume_settle().unwrap();
+            payments.sort();
+            assert_eq!(payments, expected_results);
+        }
+    }
+
+    #[test]
+    fn ledger_settle_is_deterministic() {
+        let settle = || {
+            let mut ledger = Ledger::new();
+            for (debtor, creditor, amount) in &[
+                ("A", "B", 10),
+                ("C", "D", 10),
+                ("E", "F", 7),
+                ("G", "F", 3),
+                ("H", "B", 5),
+            ] {
+                ledger
+                    .add_transaction(transaction!(debtor, creditor, (*amount, "USD")))
+                    .unwrap();
+            }
+            ledger.settle().unwrap()
+        };
+        let first = settle();
+        assert_eq!(first.len(), 5);
+        for _ in 0..20 {
+            assert_eq!(settle(), first);
+        }
+    }
+
+    #[test]
+    fn ledger_settle_pays_directly_between_two_parties() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (30, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "A", (12, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "A", (5, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("A", "C", (5, "USD")))
+            .unwrap();
+
+        let expected = vec![transaction!("A", "B", (18, "USD"))];
+        assert_eq!(ledger.settle_upto(3).unwrap(), expected);
+        assert_eq!(ledger.settle().unwrap(), expected);
+    }
+
+    // Builds a ledger from up to eight random USD debts between six parties.
+    fn random_ledger(rng: &mut impl Rng) -> Ledger {
+        let parties = ["A", "B", "C", "D", "E", "F"];
+        let mut ledger = Ledger::new();
+        for _ in 0..8 {
+            let debtor = parties[rng.gen_range(0..parties.len())];
+            let creditor = parties[rng.gen_range(0..parties.len())];
+            if debtor != creditor {
+                let amount = rng.gen_range(1..50);
+                ledger
+                    .add_transaction(transaction!(debtor, creditor, (amount, "USD")))
+                    .unwrap();
+            }
+        }
+        ledger
+    }
+
+    #[test]
+    fn ledger_settle_checked_stays_within_bound_for_random_ledgers() {
+        let mut rng = StdRng::seed_from_u64(211);
+        for _ in 0..20 {
+            let ledger = random_ledger(&mut rng);
+            assert!(ledger.settle_checked().is_ok());
+        }
+    }
+
+    #[test]
+    fn transactions_expose_their_parties_and_amount() {
+        let ledger = Ledger::from_transactions(&[
+            transaction!("A", "B", (10, "USD")),
+            transaction!("B", "C", (10, "USD")),
+        ])
+        .unwrap();
+        let payment = &ledger.settle().unwrap()[0];
+        assert_eq!(payment.debtor(), "A");
+        assert_eq!(payment.creditor(), "C");
+        assert_eq!(payment.amount(), &money!(10, "USD"));
+
+        let rent = MultiPartyTransaction::new_weighted(
+            vec![("A".to_string(), 2), ("B".to_string(), 1)],
+            vec![("C".to_string(), 1)],
+            money!(90, "USD"),
+        )
+        .unwrap();
+        assert_eq!(rent.debtors(), ["A", "B"]);
+        assert_eq!(rent.creditors(), ["C"]);
+        assert_eq!(rent.amount(), &money!(90, "USD"));
+        assert_eq!(rent.debtor_weights(), [2, 1]);
+        assert_eq!(rent.creditor_weights(), [1]);
+    }
+
+    #[test]
+    fn transactions_can_be_deduplicated_in_a_hash_set() {
+        use std::collections::HashSet;
+
+        let mut expenses = HashSet::new();
+        expenses.insert(transaction!("A", "B", (10, "USD")));
+        expenses.insert(transaction!("A", "B", ("10.00", "USD")));
+        assert_eq!(expenses.len(), 1);
+        expenses.insert(transaction!("A", "B", (10, "EUR")));
+        assert_eq!(expenses.len(), 2);
+
+        let dinner = || {
+            MultiPartyTransaction::new(
+                vec!["A".to_string(), "B".to_string()],
+                vec!["C".to_string()],
+                money!(30, "USD"),
+            )
+            .unwrap()
+        };
+        let splits: HashSet<MultiPartyTransaction> = vec![dinner(), dinner()].into_iter().collect();
+        assert_eq!(splits.len(), 1);
+    }
+
+    #[test]
+    fn ledger_add_transaction_ref_leaves_transaction_with_caller() {
+        let mut ledger = Ledger::new();
+        let mut audit_log = Vec::new();
+        let lunch = transaction!("A", "B", (10, "USD"));
+        ledger.add_transaction_ref(&lunch).unwrap();
+        audit_log.push(lunch);
+
+        assert_eq!(audit_log[0].id(), None);
+        assert_eq!(ledger.history()[0].id(), Some("1"));
+        assert_eq!(ledger.balance_of("A"), money!(-10, "USD"));
+        assert!(ledger
+            .add_transaction_ref(&transaction!("A", "B", (10, "EUR")))
+            .is_err());
+    }
+
+    #[test]
+    fn ledger_restore_undoes_changes_since_snapshot() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        let snapshot = ledger.snapshot();
+
+        ledger
+            .add_transaction(transaction!("B", "C", (25, "USD")))
+            .unwrap();
+        ledger.consume_settle().unwrap();
+        assert_eq!(
+            snapshot.ledger().settle().unwrap(),
+            vec![transaction!("A", "B", (10, "USD"))]
+        );
+
+        ledger.restore(snapshot.clone());
+        assert_eq!(ledger.balance_of("A"), money!(-10, "USD"));
+        assert!(!ledger.is_known_party("C"));
+        assert_eq!(ledger.history().len(), 1);
+        ledger
+            .add_transaction(transaction!("B", "C", (5, "USD")))
+            .unwrap();
+        assert_eq!(ledger.history()[1].id(), Some("2"));
+    }
+
+    #[test]
+    fn ledger_settles_registered_currencies() {
+        register_currency("HP", 0, "pts").unwrap();
+        let mut ledger = Ledger::new();
+        ledger
+            .add_multi_party_transaction(
+                MultiPartyTransaction::new(
+                    vec!["A".to_string(), "B".to_string(), "C".to_string()],
+                    vec!["D".to_string()],
+                    (10, "HP").into_money().unwrap(),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("D", "C", ("4", "hp")))
+            .unwrap();
+        let mut payments = ledger.settle().unwrap();
+        payments.sort();
+        assert_eq!(
+            payments
+                .iter()
+                .map(Transaction::to_string)
+                .collect::<Vec<_>>(),
+            vec!["A owes D 3pts", "B owes D 3pts"]
+        );
+    }
+
+    #[test]
+    fn ledger_add_refund_reverses_an_expense() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (30, "USD")))
+            .unwrap();
+        ledger
+            .add_refund("A".to_string(), "B".to_string(), money!(10, "USD"))
+            .unwrap();
+        assert_eq!(ledger.balance_of("A"), money!(-20, "USD"));
+        assert_eq!(ledger.balance_of("B"), money!(20, "USD"));
+        assert_eq!(ledger.history()[1].debtor, "B");
+        assert_eq!(ledger.history()[1].creditor, "A");
+
+        assert!(ledger
+            .add_refund("A".to_string(), "B".to_string(), money!(-10, "USD"))
+            .is_err());
+        assert_eq!(
+            ledger.settle().unwrap(),
+            vec![transaction!("A", "B", (20, "USD"))]
+        );
+    }
+
+    #[test]
+    fn ledger_apply_settlement_cancels_random_ledgers() {
+        let mut rng = StdRng::seed_from_u64(285);
+        for _ in 0..20 {
+            let ledger = random_ledger(&mut rng);
+            let mut replay = ledger.clone();
+            let payments = ledger.settle().unwrap();
+            replay.apply(&payments).unwrap();
+            assert!(replay.is_balanced());
+            assert!(replay.to_vector().iter().all(|(_, b)| b.is_zero()));
+            assert_eq!(replay.history().len(), ledger.history().len());
+        }
+
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        let payments = [
+            transaction!("A", "B", (5, "USD")),
+            transaction!("A", "B", (5, "EUR")),
+        ];
+        assert!(matches!(
+            ledger.apply(&payments),
+            Err(TransactionError::CurrencyMismatch(_))
+        ));
+        assert_eq!(ledger.balance_of("A"), money!(-10, "USD"));
+    }
+
+    #[test]
+    fn ledger_settle_optimal_finds_fewest_payments() {
+        // The six balances split into two zero-sum groups at most, such as A,F and B,C,D,E, so
+        // four payments is the minimum.
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transactions(vec![
+                transaction!("A", "B", (5, "USD")),
+                transaction!("C", "E", (3, "USD")),
+                transaction!("D", "E", (4, "USD")),
+                transaction!("E", "F", (5, "USD")),
+            ])
+            .unwrap();
+
+        let payments = ledger.settle_optimal().unwrap();
+        assert_eq!(payments.len(), 4);
+        ledger.apply(&payments).unwrap();
+        assert!(ledger.to_vector().iter().all(|(_, b)| b.is_zero()));
+
+        let mut ledger = Ledger::new();
+        for i in 0..=MAX_OPTIMAL_PARTIES {
+            ledger
+                .add_transaction(transaction!(format!("P{}", i), "Q", (1, "USD")))
+                .unwrap();
+        }
+        assert!(matches!(
+            ledger.settle_optimal(),
+            Err(SettleError::TooManyParties { parties: 22, .. })
+        ));
+    }
+
+    #[test]
+    fn ledger_min_transactions_matches_known_optimum() {
+        // A,F and B,C,D,E are the two zero-sum groups, so four payments is the minimum.
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transactions(vec![
+                transaction!("A", "B", (5, "USD")),
+                transaction!("C", "E", (3, "USD")),
+                transaction!("D", "E", (4, "USD")),
+                transaction!("E", "F", (5, "USD")),
+            ])
+            .unwrap();
+        let balances = ledger.to_vector();
+        assert_eq!(ledger.min_transactions().unwrap(), 4);
+        assert_eq!(ledger.to_vector(), balances);
+        assert_eq!(ledger.settle_optimal().unwrap().len(), 4);
+
+        // Two pairs that cancel out need one payment each.
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transactions(vec![
+                transaction!("A", "B", (10, "USD")),
+                transaction!("C", "D", (7, "USD")),
+            ])
+            .unwrap();
+        assert_eq!(ledger.min_transactions().unwrap(), 2);
+
+        // A cycle nets out to nothing, and an empty ledger needs no payments.
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transactions(vec![
+                transaction!("A", "B", (10, "USD")),
+                transaction!("B", "C", (10, "USD")),
+                transaction!("C", "A", (10, "USD")),
+            ])
+            .unwrap();
+        assert_eq!(ledger.min_transactions().unwrap(), 0);
+        assert_eq!(Ledger::new().min_transactions().unwrap(), 0);
+    }
+
+    #[test]
+    fn ledger_settle_greedy_scales_to_many_parties() {
+        let mut ledger = Ledger::new();
+        for i in 0..1000 {
+            let creditor = format!("P{}", (i * 7 + 1) % 1000);
+            ledger
+                .add_transaction(transaction!(
+                    format!("P{}", i),
+                    creditor,
+                    (i % 97 + 1, "USD")
+                ))
+                .unwrap();
+        }
+        let parties = ledger
+            .to_vector()
+            .iter()
+            .filter(|(_, b)| !b.is_zero())
+            .count();
+
+        let payments = ledger.settle_greedy().unwrap();
+        assert!(payments.len() < parties);
+        ledger.apply(&payments).unwrap();
+        assert!(ledger.to_vector().iter().all(|(_, b)| b.is_zero()));
+    }
+
+    #[test]
+    fn ledger_strategies_agree_detects_when_search_saves_payments() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        assert!(ledger.strategies_agree().unwrap());
+
+        // The search pays A->B and C,D->E in three payments, while paying the largest balances
+        // first starts with A->E and takes four.
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transactions(vec![
+                transaction!("A", "B", (4, "USD")),
+                transaction!("C", "E", (3, "USD")),
+                transaction!("D", "E", (3, "USD")),
+            ])
+            .unwrap();
+        assert!(!ledger.strategies_agree().unwrap());
+        assert_eq!(ledger.to_vector().len(), 5);
+    }
+
+    #[test]
+    fn ledger_simplification_savings_compares_against_paying_each_debt() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "A", (5, "USD")))
+            .unwrap();
+        ledger
+            .add_multi_party_transaction(
+                MultiPartyTransaction::new(
+                    vec!["B".to_string()],
+                    vec!["A".to_string()],
+                    money!(5, "USD"),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        // Four individual payments (A->B, B->C, C->A and B->A) settle as a single B->C.
+        assert_eq!(ledger.simplification_savings().unwrap(), 3);
+        assert_eq!(ledger.history().len(), 3);
+    }
+
+    #[test]
+    fn ledger_settlement_search_cost_sums_combinations() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (2, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "D", (3, "USD")))
+            .unwrap();
+        assert_eq!(ledger.settlement_search_cost(0), 0);
+        assert_eq!(ledger.settlement_search_cost(3), 4 + 6 + 4);
+        assert_eq!(ledger.settlement_search_cost(10), 4 + 6 + 4 + 1);
+    }
+
+    #[test]
+    fn ledger_balance_of_and_parties_read_balances() {
+        let mut ledger = Ledger::new();
+        assert_eq!(ledger.balance_of("A"), money!(0, "USD"));
+        ledger
+            .add_transaction(transaction!("B", "A", (10, "GBP")))
+            .unwrap();
+
+        assert_eq!(ledger.balance_of("A"), money!(10, "GBP"));
+        assert_eq!(ledger.balance_of("B"), money!(-10, "GBP"));
+        assert_eq!(ledger.balance_of("C"), money!(0, "GBP"));
+        assert_eq!(ledger.parties(), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn ledger_is_balanced_and_total_debt_summarize_balances() {
+        let mut ledger = Ledger::new();
+        assert!(ledger.is_balanced());
+        assert!(ledger.total_debt().is_zero());
+
+        ledger
+            .add_transaction(transaction!("A", "B", (30, "GBP")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "B", (12, "GBP")))
+            .unwrap();
+        assert!(ledger.is_balanced());
+        assert_eq!(ledger.total_debt(), money!(42, "GBP"));
+
+        ledger.map.insert("D".to_string(), money!(5, "GBP"));
+        assert!(!ledger.is_balanced());
+        assert_eq!(ledger.total_debt(), money!(42, "GBP"));
+    }
+
+    #[test]
+    fn ledger_from_transactions_borrows_expenses() {
+        let expenses = vec![
+            transaction!("A", "B", (10, "USD")),
+            transaction!("B", "A", (4, "USD")),
+        ];
+        let ledger = Ledger::from_transactions(&expenses).unwrap();
+        assert_eq!(ledger.transaction_count(), 2);
+        assert_eq!(expenses.len(), 2);
+        assert_eq!(
+            ledger.settle().unwrap(),
+            vec![transaction!("A", "B", (6, "USD"))]
+        );
+
+        let mixed = vec![
+            transaction!("A", "B", (10, "USD")),
+            transaction!("A", "B", (10, "GBP")),
+        ];
+        assert!(Ledger::from_transactions(&mixed).is_err());
+    }
+
+    #[test]
+    fn ledger_iter_borrows_balances() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        let mut balances: Vec<(&str, &Money)> = ledger.iter().collect();
+        balances.sort();
+        assert_eq!(
+            balances,
+            vec![("A", &money!(-10, "USD")), ("B", &money!(10, "USD"))]
+        );
+    }
+
+    #[test]
+    fn ledger_is_known_party_checks_for_entries() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("Alice", "Bob", (10, "USD")))
+            .unwrap();
+        assert!(ledger.is_known_party("Alice"));
+        assert!(ledger.is_known_party("Bob"));
+        assert!(!ledger.is_known_party("Alcie"));
+    }
+
+    #[test]
+    fn ledger_excludes_non_settleable_transactions_from_settlement() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (5, "USD")).with_settleable(false))
+            .unwrap();
+
+        assert_eq!(ledger.history().len(), 2);
+        assert!(!ledger.history()[1].settleable());
+        assert!(!ledger.is_known_party("C"));
+        assert_eq!(
+            ledger.settle().unwrap(),
+            vec![transaction!("A", "B", (10, "USD"))]
+        );
+    }
+
+    #[test]
+    fn ledger_removes_transactions_by_id() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (4, "USD")).with_id("taxi".to_string()))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "A", (3, "USD")))
+            .unwrap();
+
+        assert_eq!(
+            ledger.get_transaction("1").unwrap().to_string(),
+            "A owes B $10.00"
+        );
+        assert_eq!(
+            ledger.get_transaction("2").unwrap().to_string(),
+            "C owes A $3.00"
+        );
+        assert_eq!(ledger.get_transaction("taxi").unwrap().id(), Some("taxi"));
+
+        let removed = ledger.remove_transaction_by_id("taxi").unwrap();
+        assert_eq!(removed.to_string(), "B owes C $4.00");
+        assert!(ledger.get_transaction("taxi").is_none());
+        assert!(ledger.remove_transaction_by_id("taxi").is_none());
+
+        let mut payments = ledger.settle().unwrap();
+        payments.sort();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "B", (7, "USD")),
+                transaction!("C", "B", (3, "USD"))
+            ]
+        );
+    }
+
+    #[test]
+    fn ledger_remove_transaction_reverses_add() {
+        let mut ledger = Ledger::new();
+        let dinner = MultiPartyTransaction::new(
+            vec!["A".to_string(), "B".to_string()],
+            vec!["C".to_string()],
+            money!(20, "USD"),
+        )
+        .unwrap();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger.add_multi_party_transaction(dinner.clone()).unwrap();
+
+        ledger
+            .remove_transaction(&transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        assert!(ledger.history().is_empty());
+        assert_eq!(ledger.balance_of("A"), money!(-10, "USD"));
+        assert_eq!(ledger.balance_of("B"), money!(-10, "USD"));
+
+        ledger.remove_multi_party_transaction(&dinner).unwrap();
+        assert!(ledger.multi_party_history().is_empty());
+        assert!(ledger.parties().is_empty());
+
+        // Removing a transaction that was never added keeps the ledger balanced.
+        ledger
+            .remove_transaction(&transaction!("D", "E", (5, "USD")))
+            .unwrap();
+        assert_eq!(ledger.balance_of("D"), money!(5, "USD"));
+        assert_eq!(ledger.balance_of("E"), money!(-5, "USD"));
+        assert!(ledger
+            .remove_transaction(&transaction!("D", "E", (5, "GBP")))
+            .is_err());
+    }
+
+    #[test]
+    fn ledger_subset_keeps_only_named_parties() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "B", (5, "USD")))
+            .unwrap();
+
+        let subset = ledger.subset(&["A".to_string(), "B".to_string(), "D".to_string()]);
+        let mut balances = subset.to_vector();
+        balances.sort();
+        assert_eq!(
+            balances,
+            vec![
+                ("A".to_string(), money!(-10, "USD")),
+                ("B".to_string(), money!(15, "USD"))
+            ]
+        );
+        assert!(subset.history().is_empty());
+    }
+
+    #[test]
+    fn ledger_merge_sums_balances_of_shared_parties() {
+        let mut paris = Ledger::new();
+        paris
+            .add_transaction(transaction!("A", "B", (30, "USD")))
+            .unwrap();
+        let mut rome = Ledger::new();
+        rome.add_transaction(transaction!("A", "C", (20, "USD")))
+            .unwrap();
+        rome.add_transaction(transaction!("B", "C", (5, "USD")))
+            .unwrap();
+
+        let trips = Ledger::merged(paris, rome).unwrap();
+        assert_eq!(trips.balance_of("A"), money!(-50, "USD"));
+        assert_eq!(trips.balance_of("B"), money!(25, "USD"));
+        assert_eq!(trips.balance_of("C"), money!(25, "USD"));
+        assert_eq!(trips.history().len(), 3);
+        assert_eq!(trips.settle().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn ledger_merge_renumbers_transaction_ids() {
+        let mut paris = Ledger::new();
+        paris
+            .add_transaction(transaction!("A", "B", (30, "USD")))
+            .unwrap();
+        paris
+            .add_transaction(transaction!("B", "C", (10, "USD")))
+            .unwrap();
+        let mut rome = Ledger::new();
+        rome.add_transaction(transaction!("C", "A", (20, "USD")))
+            .unwrap();
+        rome.add_transaction(transaction!("B", "A", (5, "USD")).with_id("taxi".to_string()))
+            .unwrap();
+
+        paris.merge(rome).unwrap();
+        let ids: Vec<Option<&str>> = paris.history().iter().map(Transaction::id).collect();
+        assert_eq!(ids, vec![Some("1"), Some("2"), Some("3"), Some("taxi")]);
+
+        let removed = paris.remove_transaction_by_id("3").unwrap();
+        assert_eq!(
+            removed,
+            transaction!("C", "A", (20, "USD")).with_id("3".to_string())
+        );
+        assert_eq!(paris.get_transaction("1").unwrap().creditor(), "B");
+        paris
+            .add_transaction(transaction!("A", "C", (1, "USD")))
+            .unwrap();
+        assert_eq!(paris.history().last().unwrap().id(), Some("4"));
+    }
+
+    #[test]
+    fn ledger_merge_rejects_another_currency() {
+        let mut dollars = Ledger::new();
+        dollars
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        let mut pounds = Ledger::new();
+        pounds
+            .add_transaction(transaction!("A", "B", (10, "GBP")))
+            .unwrap();
+
+        assert!(matches!(
+            dollars.merge(pounds),
+            Err(TransactionError::CurrencyMismatch(_))
+        ));
+        assert_eq!(dollars.balance_of("A"), money!(-10, "USD"));
+        assert!(dollars.merge(Ledger::new()).is_ok());
+    }
+
+    #[test]
+    fn ledger_stores_metadata() {
+        let mut ledger = Ledger::new();
+        ledger.set_metadata("trip".to_string(), "Lisbon".to_string());
+        ledger.set_metadata("trip".to_string(), "Porto".to_string());
+        assert_eq!(ledger.get_metadata("trip"), Some("Porto"));
+        assert_eq!(ledger.get_metadata("owner"), None);
+    }
+
+    #[test]
+    fn ledger_settle_with_bound_reports_lower_bound() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (20, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (50, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "A", (35, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("D", "E", (5, "USD")))
+            .unwrap();
+
+        let (payments, bound) = ledger.settle_with_bound().unwrap();
+        assert_eq!(bound, 3);
+        assert!(payments.len() >= bound);
+    }
+
+    #[test]
+    fn ledger_settle_with_summary_reports_totals() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("Alice", "Bob", (20, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("Bob", "Charlie", (50, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("Charlie", "Alice", (35, "USD")))
+            .unwrap();
+
+        let (payments, summary) = ledger.settle_with_summary().unwrap();
+        assert_eq!(payments.len(), 2);
+        assert_eq!(
+            summary,
+            SettleSummary {
+                input_party_count: 3,
+                payment_count: 2,
+                total_transferred: money!(30, "USD"),
+                largest_payment: Some(money!(15, "USD")),
+            }
+        );
+
+        let (_, empty) = Ledger::new().settle_with_summary().unwrap();
+        assert_eq!(empty.payment_count, 0);
+        assert!(empty.total_transferred.is_zero());
+        assert_eq!(empty.largest_payment, None);
+    }
+
+    #[test]
+    fn ledger_settle_steps_walks_through_each_payment() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (20, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (20, "USD")))
+            .unwrap();
+
+        let steps = ledger.settle_steps().unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].payment, None);
+        assert_eq!(
+            steps[0].balances,
+            vec![
+                ("A".to_string(), money!(-20, "USD")),
+                ("B".to_string(), money!(0, "USD")),
+                ("C".to_string(), money!(20, "USD")),
+            ]
+        );
+        assert_eq!(steps[1].payment, Some(transaction!("A", "C", (20, "USD"))));
+        assert!(steps[1]
+            .balances
+            .iter()
+            .all(|(_, balance)| balance.is_zero()));
+    }
+
+    #[test]
+    fn ledger_settle_concurrent_batches_payments_without_shared_parties() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "D", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("E", "B", (5, "USD")))
+            .unwrap();
+
+        let batches = ledger.settle_concurrent().unwrap();
+        assert_eq!(batches.iter().map(Vec::len).sum::<usize>(), 3);
+        assert_eq!(batches.len(), 2);
+        for batch in &batches {
+            let mut parties: Vec<&String> = batch
                 .iter()
-                .fold(money!(0, "USD"), |acc, x| acc + x.1.clone())
-                .is_zero()
+                .flat_map(|payment| vec![&payment.debtor, &payment.creditor])
+                .collect();
+            let count = parties.len();
+            parties.sort();
+            parties.dedup();
+            assert_eq!(parties.len(), count);
+        }
+    }
+
+    #[test]
+    fn ledger_settle_to_string_lists_sorted_payments() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("C", "B", (5, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("A", "B", (1000, "USD")))
+            .unwrap();
+
+        assert_eq!(
+            ledger.settle_to_string().unwrap(),
+            "A owes B $1,000.00\nC owes B $5.00"
+        );
+        ledger.consume_settle().unwrap();
+        assert_eq!(ledger.settle_to_string().unwrap(), "All settled up.");
+    }
+
+    #[test]
+    fn ledger_full_report_bundles_balances_and_settlement() {
+        let mut ledger = Ledger::new();
+        ledger.set_metadata("trip".to_string(), "Lisbon".to_string());
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "B", (5, "USD")))
+            .unwrap();
+
+        let report = ledger.full_report().unwrap();
+        assert_eq!(report.balances[0], ("A".to_string(), money!(-10, "USD")));
+        assert_eq!(report.payment_count, 2);
+        assert_eq!(report.payments.len(), 2);
+        assert_eq!(report.total_debt, Some(money!(15, "USD")));
+        assert_eq!(report.metadata["trip"], "Lisbon");
+        assert_eq!(Ledger::new().full_report().unwrap().total_debt, None);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn ledger_full_report_serializes_money_as_strings() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+
+        let json = serde_json::to_value(ledger.full_report().unwrap()).unwrap();
+        let balance = serde_json::json!({"amount": "-10.00", "currency": "USD"});
+        let amount = serde_json::json!({"amount": "10.00", "currency": "USD"});
+        assert_eq!(json["balances"][0], serde_json::json!(["A", balance]));
+        assert_eq!(json["payments"][0]["amount"], amount);
+        assert_eq!(json["total_debt"], amount);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn ledger_round_trips_through_json() {
+        let mut ledger = Ledger::new();
+        ledger.set_metadata("trip".to_string(), "Lisbon".to_string());
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "EUR")))
+            .unwrap();
+        ledger
+            .add_multi_party_transaction(
+                MultiPartyTransaction::new(
+                    vec!["C".to_string(), "D".to_string()],
+                    vec!["E".to_string()],
+                    Money::from_minor(2999, Currency::get(EUR)),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let json = serde_json::to_string(&ledger).unwrap();
+        let restored: Ledger = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.history(), ledger.history());
+        assert_eq!(restored.multi_party_history(), ledger.multi_party_history());
+        assert_eq!(restored.get_metadata("trip"), Some("Lisbon"));
+        assert_eq!(restored.currency(), Some(Currency::get(EUR)));
+
+        let mut expected = ledger.settle().unwrap();
+        let mut payments = restored.settle().unwrap();
+        expected.sort();
+        payments.sort();
+        assert_eq!(payments, expected);
+
+        let transaction = serde_json::to_value(transaction!("A", "B", (10, "EUR"))).unwrap();
+        assert_eq!(
+            transaction["amount"],
+            serde_json::json!({"amount": "10.00", "currency": "EUR"})
+        );
+    }
+
+    #[test]
+    fn ledger_settle_stats_does_not_mutate_ledger() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (20, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (50, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "A", (35, "USD")))
+            .unwrap();
+
+        let stats = ledger.settle_stats(10).unwrap();
+        assert_eq!(stats.trials, 10);
+        assert_eq!(stats.min_payments, 2);
+        assert_eq!(stats.max_payments, 2);
+        assert!((stats.mean_payments - 2.0).abs() < f64::EPSILON);
+        assert_eq!(ledger.settle().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn ledger_settle_proportional_splits_debts_by_credit_share() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "C", (30, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "D", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "D", (10, "USD")))
+            .unwrap();
+
+        let mut payments = ledger.settle_proportional().unwrap();
+        payments.sort();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "C", (15, "USD")),
+                transaction!("A", "D", (15, "USD")),
+                transaction!("B", "C", (5, "USD")),
+                transaction!("B", "D", (5, "USD")),
+            ]
+        );
+    }
+
+    #[test]
+    fn ledger_settle_proportional_reconciles_uneven_shares() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "C", (1, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("A", "D", (1, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "E", (1, "USD")))
+            .unwrap();
+
+        let payments = ledger.settle_proportional().unwrap();
+        let total = payments
+            .iter()
+            .fold(money!(0, "USD"), |acc, x| acc + x.amount.clone());
+        assert_eq!(total, money!(3, "USD"));
+        ledger.apply(&payments).unwrap();
+        assert!(ledger
+            .to_vector()
+            .iter()
+            .all(|(_, balance)| balance.is_zero()));
+    }
+
+    #[test]
+    fn ledger_settle_to_creditor_routes_through_named_party() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "D", (5, "USD")))
+            .unwrap();
+
+        let mut payments = ledger.settle_to_creditor("B").unwrap();
+        payments.sort();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "B", (10, "USD")),
+                transaction!("B", "D", (5, "USD")),
+                transaction!("C", "B", (5, "USD")),
+            ]
+        );
+        ledger.apply(&payments).unwrap();
+        assert!(ledger
+            .to_vector()
+            .iter()
+            .all(|(_, balance)| balance.is_zero()));
+    }
+
+    #[test]
+    fn ledger_settle_to_creditor_rejects_unknown_party() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        assert!(matches!(
+            ledger.settle_to_creditor("Z"),
+            Err(SettleError::UnknownParty(_))
+        ));
+    }
+
+    #[test]
+    fn ledger_settle_with_reserve_pays_whole_amounts() {
+        let mut ledger = Ledger::new();
+        for creditor in &["B", "C"] {
+            let amount = money!("10.40", "USD");
+            let transaction = Transaction::new("A".to_string(), creditor.to_string(), amount);
+            ledger.add_transaction(transaction.unwrap()).unwrap();
+        }
+
+        let mut payments = ledger.settle_with_reserve("R").unwrap();
+        payments.sort();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "B", (10, "USD")),
+                transaction!("A", "C", (10, "USD")),
+                transaction!("A", "R", (1, "USD")),
+            ]
+        );
+    }
+
+    #[test]
+    fn ledger_settles_fractional_balances_exactly() {
+        let build = || {
+            let mut ledger = Ledger::new();
+            for (debtor, creditor, amount) in
+                &[("A", "D", "10.50"), ("D", "B", "5.25"), ("D", "C", "5.25")]
             {
-                zero_sum_combinations.push(item);
+                let amount = Money::from_string(amount.to_string(), "USD".to_string()).unwrap();
+                let transaction =
+                    Transaction::new(debtor.to_string(), creditor.to_string(), amount).unwrap();
+                ledger.add_transaction(transaction).unwrap();
+            }
+            ledger
+        };
+        let settlements = vec![
+            build().settle().unwrap(),
+            build().settle_greedy().unwrap(),
+            build().settle_optimal().unwrap(),
+        ];
+        for mut payments in settlements {
+            payments.sort();
+            let expected: Vec<Transaction> = ["B", "C"]
+                .iter()
+                .map(|creditor| {
+                    Transaction::new("A".to_string(), creditor.to_string(), money!("5.25", "USD"))
+                        .unwrap()
+                })
+                .collect();
+            assert_eq!(payments, expected);
+            let total = payments
+                .iter()
+                .fold(money!(0, "USD"), |total, p| total + p.amount.clone());
+            assert_eq!(total, money!("10.50", "USD"));
+        }
+    }
+
+    #[test]
+    fn ledger_settles_in_euros() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (20, "EUR")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (20, "EUR")))
+            .unwrap();
+        assert_eq!(
+            ledger.settle().unwrap(),
+            vec![transaction!("A", "C", (20, "EUR"))]
+        );
+    }
+
+    #[test]
+    fn ledger_rejects_transactions_in_another_currency() {
+        let mut ledger = Ledger::new();
+        assert_eq!(ledger.currency(), None);
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "GBP")))
+            .unwrap();
+        assert_eq!(ledger.currency(), Some(Currency::get(GBP)));
+
+        let result = ledger.add_transaction(transaction!("B", "C", (10, "USD")));
+        match result.unwrap_err() {
+            TransactionError::CurrencyMismatch(e) => {
+                assert_eq!(e.to_string(), "Expected an amount in GBP, found USD")
             }
+            e => panic!("unexpected error {}", e),
         }
-        zero_sum_combinations
+        let multi = MultiPartyTransaction::new(
+            vec!["A".to_string()],
+            vec!["C".to_string()],
+            money!(10, "USD"),
+        )
+        .unwrap();
+        assert!(ledger.add_multi_party_transaction(multi).is_err());
+
+        assert_eq!(ledger.history().len(), 1);
+        assert!(!ledger.is_known_party("C"));
+        assert_eq!(
+            ledger.settle().unwrap(),
+            vec![transaction!("A", "B", (10, "GBP"))]
+        );
+    }
+
+    #[test]
+    fn ledger_settle_returns_error_if_unbalanced() {
+        let mut ledger = Ledger::new();
+        ledger
+            .map
+            .entry("A".to_string())
+            .or_insert(money!(10, "USD"));
+        ledger
+            .add_transaction(transaction!("B", "C", (5, "USD")))
+            .unwrap();
+        match ledger.settle() {
+            Err(SettleError::Unbalanced { residual }) => assert_eq!(residual, money!(10, "USD")),
+            _ => panic!("expected an unbalanced error"),
+        }
+        assert!(ledger.settle_upto(1).is_err());
+        assert_eq!(ledger.to_vector().len(), 3);
+    }
+
+    #[test]
+    fn ledger_settle_sorted_orders_by_debtor_then_creditor() {
+        let ledger = Ledger::from_transactions(&[
+            transaction!("C", "A", (5, "USD")),
+            transaction!("B", "D", (3, "USD")),
+            transaction!("B", "A", (7, "USD")),
+        ])
+        .unwrap();
+        let expected = vec![
+            transaction!("B", "A", (10, "USD")),
+            transaction!("C", "A", (2, "USD")),
+            transaction!("C", "D", (3, "USD")),
+        ];
+        assert_eq!(ledger.settle_sorted().unwrap(), expected);
+        let mut sorted = ledger.settle().unwrap();
+        sorted.sort();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn ledger_settle_leaves_ledger_intact() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (10, "USD")))
+            .unwrap();
+        let payments = ledger.settle().unwrap();
+        assert_eq!(payments, vec![transaction!("A", "C", (10, "USD"))]);
+        assert_eq!(ledger.settle().unwrap(), payments);
+
+        ledger
+            .add_transaction(transaction!("C", "A", (4, "USD")))
+            .unwrap();
+        assert_eq!(
+            ledger.consume_settle().unwrap(),
+            vec![transaction!("A", "C", (6, "USD"))]
+        );
+        assert!(ledger.to_vector().iter().all(|(_, b)| b.is_zero()));
+        assert_eq!(ledger.history().len(), 3);
+        assert!(ledger.settle().unwrap().is_empty());
+    }
+
+    #[test]
+    fn ledger_settle_variants_leave_ledger_intact() {
+        let ledger = Ledger::from_transactions(&[
+            transaction!("A", "B", ("10.01", "USD")),
+            transaction!("B", "C", (4, "USD")),
+            transaction!("D", "C", (3, "USD")),
+        ])
+        .unwrap();
+        let balances = ledger.to_vector();
+
+        ledger.settle_lenient();
+        ledger.settle_with_max(money!(2, "USD")).unwrap();
+        ledger.settle_checked().unwrap();
+        ledger.full_report().unwrap();
+        ledger.settle_to_creditor("B").unwrap();
+        ledger.settle_via_hub("Treasurer").unwrap();
+        ledger.settle_with_reserve("Treasurer").unwrap();
+        ledger.settle_ignoring_below(money!(1, "USD")).unwrap();
+        ledger.settle_proportional().unwrap();
+        ledger.settle_upto(2).unwrap();
+        ledger.settle_optimal().unwrap();
+        ledger.settle_greedy().unwrap();
+        assert_eq!(ledger.to_vector(), balances);
+    }
+
+    #[test]
+    fn ledger_with_seed_settles_reproducibly() {
+        let settle_with = |seed| {
+            let mut ledger = Ledger::with_seed(seed);
+            ledger
+                .add_transactions(vec![
+                    transaction!("A", "D", (10, "USD")),
+                    transaction!("B", "E", (20, "USD")),
+                    transaction!("C", "F", (30, "USD")),
+                    transaction!("A", "E", (5, "USD")),
+                    transaction!("C", "D", (7, "USD")),
+                ])
+                .unwrap();
+            let mut payments = ledger.settle().unwrap();
+            payments.sort();
+            payments
+        };
+        let mut settlements = Vec::new();
+        for seed in 0..20 {
+            let payments = settle_with(seed);
+            assert_eq!(settle_with(seed), payments);
+            assert_eq!(
+                Money::try_sum(
+                    Currency::get(USD),
+                    payments.iter().map(|p| p.amount().clone())
+                ),
+                Ok(money!(72, "USD"))
+            );
+            settlements.push(payments);
+        }
+        settlements.dedup();
+        assert!(settlements.len() > 1);
+    }
+
+    #[test]
+    fn ledger_settle_lenient_returns_residuals() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "D", (5, "USD")))
+            .unwrap();
+        ledger.map.insert("E".to_string(), money!("-0.01", "USD"));
+
+        let (payments, residuals) = ledger.settle_lenient();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "B", (10, "USD")),
+                transaction!("C", "D", (5, "USD"))
+            ]
+        );
+        assert_eq!(residuals, vec![("E".to_string(), money!("-0.01", "USD"))]);
+        assert_eq!(ledger.balance_of("E"), money!("-0.01", "USD"));
+
+        let mut balanced = Ledger::new();
+        balanced
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        let (payments, residuals) = balanced.settle_lenient();
+        assert_eq!(payments, vec![transaction!("A", "B", (10, "USD"))]);
+        assert!(residuals.is_empty());
+    }
+
+    #[test]
+    fn ledger_settle_with_max_splits_large_payments() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (2500, "GBP")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "D", (1000, "GBP")))
+            .unwrap();
+        assert!(matches!(
+            ledger.settle_with_max(money!(1000, "USD")),
+            Err(SettleError::InvalidMaxPayment { .. })
+        ));
+        assert!(matches!(
+            ledger.settle_with_max(money!(0, "GBP")),
+            Err(SettleError::InvalidMaxPayment { .. })
+        ));
+
+        assert_eq!(
+            ledger.settle_with_max(money!(1000, "GBP")).unwrap(),
+            vec![
+                transaction!("A", "B", (1000, "GBP")),
+                transaction!("A", "B", (1000, "GBP")),
+                transaction!("A", "B", (500, "GBP")),
+                transaction!("C", "D", (1000, "GBP")),
+            ]
+        );
+        assert!(ledger.is_balanced());
+    }
+
+    #[test]
+    fn ledger_flow_between_totals_gross_amounts() {
+        let mut ledger = Ledger::from_transactions(&[
+            transaction!("Alice", "Bob", (10, "USD")),
+            transaction!("Bob", "Alice", (4, "USD")),
+            transaction!("Alice", "Bob", ("2.50", "USD")),
+        ])
+        .unwrap();
+        ledger
+            .add_multi_party_transaction(
+                MultiPartyTransaction::new_weighted(
+                    vec![("Alice".to_string(), 1), ("Carol".to_string(), 1)],
+                    vec![("Bob".to_string(), 3), ("Dan".to_string(), 1)],
+                    money!(40, "USD"),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(ledger.flow_between("Alice", "Bob"), money!("27.50", "USD"));
+        assert_eq!(ledger.flow_between("Bob", "Alice"), money!(4, "USD"));
+        assert_eq!(ledger.flow_between("Alice", "Dan"), money!(5, "USD"));
+        assert!(ledger.flow_between("Dan", "Alice").is_zero());
+    }
+
+    #[test]
+    fn ledger_flow_between_handles_weights_summing_past_i32() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_multi_party_transaction(
+                MultiPartyTransaction::new_weighted(
+                    vec![("Alice".to_string(), 1)],
+                    vec![("Bob".to_string(), i32::MAX), ("Dan".to_string(), 1)],
+                    money!(10, "USD"),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let total = ledger.flow_between("Alice", "Bob") + ledger.flow_between("Alice", "Dan");
+        assert!(total.approx_eq(
+            &money!(10, "USD"),
+            Money::from_decimal(Decimal::new(1, 4), Currency::get(USD))
+        ));
+        assert!(ledger.flow_between("Alice", "Dan").is_positive());
+    }
+
+    #[test]
+    fn ledger_settle_ignoring_below_sweeps_tiny_balances() {
+        let mut ledger = Ledger::new();
+        for (party, balance) in [
+            ("A", "-10.03"),
+            ("B", "5"),
+            ("C", "5"),
+            ("D", "0.01"),
+            ("E", "0.01"),
+            ("F", "0.01"),
+        ] {
+            ledger.map.insert(party.to_string(), money!(balance, "USD"));
+        }
+        assert!(matches!(
+            ledger.settle_ignoring_below(money!(0, "USD")),
+            Err(SettleError::InvalidThreshold { .. })
+        ));
+
+        let balances = ledger.to_vector();
+        let mut payments = ledger.settle_ignoring_below(money!("0.05", "USD")).unwrap();
+        payments.sort();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "B", (5, "USD")),
+                transaction!("A", "C", (5, "USD")),
+            ]
+        );
+        assert_eq!(ledger.to_vector(), balances);
+    }
+
+    #[test]
+    fn ledger_clear_resets_for_reuse() {
+        let mut ledger = Ledger::new();
+        assert!(ledger.is_empty());
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger.set_metadata("trip".to_string(), "Lisbon".to_string());
+        assert_eq!(ledger.len(), 2);
+        ledger.consume_settle().unwrap();
+        assert_eq!(ledger.len(), 2);
+
+        let capacity = ledger.map.capacity();
+        ledger.clear();
+        assert!(ledger.is_empty());
+        assert!(ledger.history().is_empty());
+        assert_eq!(ledger.get_metadata("trip"), None);
+        assert_eq!(ledger.map.capacity(), capacity);
+
+        ledger
+            .add_transaction(transaction!("C", "D", (5, "EUR")))
+            .unwrap();
+        assert_eq!(ledger.history()[0].id(), Some("1"));
+        assert_eq!(ledger.len(), 2);
+    }
+
+    #[test]
+    fn ledger_settle_via_hub_routes_through_treasurer() {
+        let mut ledger = Ledger::from_transactions(&[
+            transaction!("A", "B", (10, "USD")),
+            transaction!("C", "D", (20, "USD")),
+        ])
+        .unwrap();
+        let payments = ledger.settle_via_hub("Treasurer").unwrap();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "Treasurer", (10, "USD")),
+                transaction!("C", "Treasurer", (20, "USD")),
+                transaction!("Treasurer", "B", (10, "USD")),
+                transaction!("Treasurer", "D", (20, "USD")),
+            ]
+        );
+        let hub_net = payments.iter().fold(money!(0, "USD"), |net, p| {
+            if p.creditor() == "Treasurer" {
+                net + p.amount().clone()
+            } else {
+                net - p.amount().clone()
+            }
+        });
+        assert!(hub_net.is_zero());
+        assert_eq!(ledger.len(), 4);
+        ledger.apply(&payments).unwrap();
+        assert!(ledger.to_vector().iter().all(|(_, b)| b.is_zero()));
+    }
+
+    #[test]
+    fn ledger_settle_with_fees_totals_fees() {
+        let ledger = Ledger::from_transactions(&[
+            transaction!("A", "C", (100, "USD")),
+            transaction!("B", "C", ("33.33", "USD")),
+        ])
+        .unwrap();
+
+        let (payments, fees) = ledger
+            .clone()
+            .settle_with_fees(FeeModel::Flat(money!("0.25", "USD")))
+            .unwrap();
+        assert_eq!(payments, ledger.settle().unwrap());
+        assert_eq!(fees, money!("0.50", "USD"));
+
+        let (_, fees) = ledger
+            .clone()
+            .settle_with_fees(FeeModel::Percentage(Decimal::new(15, 1)))
+            .unwrap();
+        assert_eq!(fees, money!("2.00", "USD"));
+
+        for fee in [
+            FeeModel::Flat(money!(1, "EUR")),
+            FeeModel::Flat(money!(-1, "USD")),
+            FeeModel::Percentage(Decimal::NEGATIVE_ONE),
+        ] {
+            match ledger.settle_with_fees(fee) {
+                Err(e @ SettleError::InvalidFee(_)) => {
+                    assert!(e.to_string().starts_with("Fee of "))
+                }
+                other => panic!("expected an invalid fee error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn ledger_settle_with_fees_takes_currency_from_fee_on_empty_ledger() {
+        let ledger = Ledger::new();
+        let (payments, fees) = ledger
+            .settle_with_fees(FeeModel::Flat(money!(1, "GBP")))
+            .unwrap();
+        assert!(payments.is_empty());
+        assert_eq!(fees, money!(0, "GBP"));
+
+        let (_, fees) = ledger
+            .settle_with_fees(FeeModel::Flat(money!(1, "EUR")))
+            .unwrap();
+        assert_eq!(fees.currency(), Currency::get(Iso::EUR));
+    }
+
+    #[test]
+    fn ledger_settle_balanced_caps_payments_at_fraction_of_total() {
+        let mut ledger = Ledger::from_transactions(&[
+            transaction!("A", "C", (100, "USD")),
+            transaction!("B", "C", (50, "USD")),
+        ])
+        .unwrap();
+        for fraction in [Decimal::ZERO, Decimal::new(11, 1)] {
+            assert!(matches!(
+                ledger.settle_balanced(fraction),
+                Err(SettleError::InvalidMaxFraction(_))
+            ));
+        }
+
+        let payments = ledger.settle_balanced(Decimal::new(4, 1)).unwrap();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "C", (60, "USD")),
+                transaction!("A", "C", (40, "USD")),
+                transaction!("B", "C", (50, "USD")),
+            ]
+        );
+        ledger.apply(&payments).unwrap();
+        assert!(ledger.to_vector().iter().all(|(_, b)| b.is_zero()));
+
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", ("0.03", "USD")))
+            .unwrap();
+        assert_eq!(
+            ledger.settle_balanced(Decimal::new(1, 2)).unwrap(),
+            vec![transaction!("A", "B", ("0.01", "USD")); 3]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn ledger_settle_unchecked_panics_if_unbalanced() {
+        let mut ledger = Ledger::new();
+        ledger
+            .map
+            .entry("A".to_string())
+            .or_insert(money!(10, "USD"));
+        ledger.settle_unchecked();
     }
 
-    // Returns vectors of keys of debtors and creditors with an active balance.s
-    fn debtor_and_creditor_keys(&self) -> (Vec<String>, Vec<String>) {
-        let mut creditors: Vec<String> = Vec::new();
-        let mut debtors: Vec<String> = Vec::new();
+    //
+    // Settlement Helper Tests
+    //
+    #[test]
+    fn ledger_settle_to_edges_renders_as_dot() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("Al \"Big\" A", "Bea", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("Bea", "Cy", ("2.50", "USD")))
+            .unwrap();
+        let expected = vec![
+            transaction!("Al \"Big\" A", "Bea", ("7.50", "USD")),
+            transaction!("Al \"Big\" A", "Cy", ("2.50", "USD")),
+        ];
+        assert_eq!(
+            ledger.settle_to_edges().unwrap(),
+            vec![
+                (
+                    "Al \"Big\" A".to_string(),
+                    "Bea".to_string(),
+                    money!("7.50", "USD")
+                ),
+                (
+                    "Al \"Big\" A".to_string(),
+                    "Cy".to_string(),
+                    money!("2.50", "USD")
+                ),
+            ]
+        );
+        assert_eq!(
+            settlement_to_dot(&expected),
+            "digraph settlement {\n    \"Al \\\"Big\\\" A\" -> \"Bea\" [label=\"$7.50\"];\n    \
+             \"Al \\\"Big\\\" A\" -> \"Cy\" [label=\"$2.50\"];\n}\n"
+        );
+        assert_eq!(ledger.settle().unwrap(), expected);
+    }
 
-        for (person, value) in &self.map {
-            if value.is_positive() {
-                creditors.push(person.clone());
-            } else if value.is_negative() {
-                debtors.push(person.clone());
-            } else {
-            }
-        }
-        (debtors, creditors)
+    #[test]
+    fn settlement_map_indexes_by_debtor_and_creditor() {
+        let payments = vec![
+            transaction!("A", "B", (5, "USD")),
+            transaction!("A", "C", (3, "USD")),
+            transaction!("D", "C", (2, "USD")),
+        ];
+        let map = settlement_map(&payments);
+        assert_eq!(map["A"]["B"], money!(5, "USD"));
+        assert_eq!(map["A"]["C"], money!(3, "USD"));
+        assert_eq!(map["D"]["C"], money!(2, "USD"));
+        assert_eq!(map["A"].len(), 2);
+        assert!(!map.contains_key("B"));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn combine_settlements_reminimizes_payments() {
+        let a = vec![transaction!("A", "B", (10, "USD"))];
+        let b = vec![
+            transaction!("B", "C", (10, "USD")),
+            transaction!("D", "E", (4, "USD")),
+        ];
+        let mut combined = combine_settlements(a, b);
+        combined.sort();
+        assert_eq!(
+            combined,
+            vec![
+                transaction!("A", "C", (10, "USD")),
+                transaction!("D", "E", (4, "USD")),
+            ]
+        );
+    }
 
-    // The settlement should always choose credits and debits that are equal over any other type.
-    // This allows two entries in the ledger to be removed in exchange for a single payment.
-    // For example, if A = -10 and B = +10, they should always first over any other possibility
     #[test]
-    fn ledger_settle_matches_equal_debts_and_credits() {
-        let mut ledger = Ledger::new();
+    fn settlement_diff_ignores_order_of_unchanged_payments() {
+        let before = vec![
+            transaction!("A", "B", (5, "USD")),
+            transaction!("C", "D", (3, "USD")),
+        ];
+        let after = vec![
+            transaction!("C", "D", (3, "USD")),
+            transaction!("A", "B", (7, "USD")),
+        ];
+        let (removed, added) = settlement_diff(&before, &after);
+        assert_eq!(removed, vec![transaction!("A", "B", (5, "USD"))]);
+        assert_eq!(added, vec![transaction!("A", "B", (7, "USD"))]);
+    }
 
-        let expected_results = vec![
-            transaction!("A", "B", (2, "USD")),
-            transaction!("C", "F", (3, "USD")),
-            transaction!("D", "F", (5, "USD")),
+    #[test]
+    fn max_payment_returns_largest_payment() {
+        let payments = vec![
+            transaction!("A", "B", (5, "USD")),
+            transaction!("C", "D", (12, "USD")),
             transaction!("E", "F", (7, "USD")),
         ];
-
-        // The worst case match (i.e. random) can accidentially find the optimal solution for small
-        // sets, so we repeat to make this very unlikely
-        for _ in 0..5 {
-            ledger.add_transaction(transaction!("A", "B", (2, "USD")));
-            ledger.add_transaction(transaction!("C", "F", (3, "USD")));
-            ledger.add_transaction(transaction!("D", "F", (5, "USD")));
-            ledger.add_transaction(transaction!("E", "F", (7, "USD")));
-            let mut payments = ledger.settle();
-            payments.sort();
-            assert_eq!(payments, expected_results);
-        }
+        assert_eq!(max_payment(&payments), Some(&payments[1]));
+        assert_eq!(max_payment(&[]), None);
     }
 
-    // Next, the settlement should always choose 3 credits and debits that are zero sum over any other.
-    // This allows three entries in the ledger to be removed in exchange for two payments.
-    // For example, if A = -10,  B = +5, C= +5.
     #[test]
-    fn ledger_settle_with_size_3_matches_groups_of_3_credits_and_debits() {
-        // Test that group matched  payments are always settled first.
+    fn settle_cents_matches_ledger_settle() {
         let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "D", (3, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "E", (2, "USD")))
+            .unwrap();
 
-        let expected_results = vec![
-            transaction!("A", "D", (3, "USD")),
-            transaction!("C", "D", (4, "USD")),
-            transaction!("E", "B", (10, "USD")),
-            transaction!("F", "B", (17, "USD")),
-            transaction!("J", "K", (20, "USD")),
-            transaction!("U", "K", (21, "USD")),
-        ];
+        let balances: Vec<(String, i64)> = ledger
+            .to_vector()
+            .into_iter()
+            .map(|(party, balance)| (party, to_units(&balance, 2) as i64))
+            .collect();
+        let mut expected: Vec<(String, String, i64)> = ledger
+            .settle()
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.debtor, t.creditor, to_units(&t.amount, 2) as i64))
+            .collect();
+        expected.sort();
 
-        // The worst case match (i.e. random) can accidentially find the optimal solution for small
-        // sets, so we repeat to make this very unlikely
-        for _ in 0..5 {
-            ledger.add_transaction(transaction!("A", "D", (3, "USD")));
-            ledger.add_transaction(transaction!("C", "D", (4, "USD")));
-            ledger.add_transaction(transaction!("E", "B", (10, "USD")));
-            ledger.add_transaction(transaction!("F", "B", (17, "USD")));
-            ledger.add_transaction(transaction!("J", "K", (20, "USD")));
-            ledger.add_transaction(transaction!("U", "K", (21, "USD")));
-
-            let mut payments = ledger.settle();
-            payments.sort();
-            assert_eq!(payments, expected_results);
+        let mut payments = settle_cents(&balances);
+        payments.sort();
+        assert_eq!(payments, expected);
+
+        // The order the balances are given in doesn't change the payments.
+        let mut rng = StdRng::seed_from_u64(244);
+        for _ in 0..20 {
+            let ledger = random_ledger(&mut rng);
+            let mut balances: Vec<(String, i64)> = ledger
+                .to_vector()
+                .into_iter()
+                .map(|(party, balance)| (party, to_units(&balance, 2) as i64))
+                .collect();
+            balances.shuffle(&mut rng);
+            let expected: Vec<(String, String, i64)> = ledger
+                .settle()
+                .unwrap()
+                .into_iter()
+                .map(|t| (t.debtor, t.creditor, to_units(&t.amount, 2) as i64))
+                .collect();
+            assert_eq!(settle_cents(&balances), expected);
         }
     }
 
+    #[test]
+    fn settle_cents_handles_extreme_balances() {
+        let balances = [
+            ("A".to_string(), i64::MIN),
+            ("B".to_string(), i64::MAX),
+            ("C".to_string(), 1),
+        ];
+        assert_eq!(
+            settle_cents(&balances),
+            vec![
+                ("A".to_string(), "B".to_string(), i64::MAX),
+                ("A".to_string(), "C".to_string(), 1),
+            ]
+        );
+    }
+
     #[test]
     #[should_panic]
-    fn ledger_settle_panics_if_unbalanced() {
+    fn settle_cents_panics_if_unbalanced() {
+        settle_cents(&[("A".to_string(), -500), ("B".to_string(), 400)]);
+    }
+
+    #[test]
+    fn ledger_activity_summary_totals_each_side_separately() {
         let mut ledger = Ledger::new();
         ledger
-            .map
-            .entry("A".to_string())
-            .or_insert(money!(10, "USD"));
-        ledger.settle();
+            .add_transaction(transaction!("B", "A", (30, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_multi_party_transaction(
+                MultiPartyTransaction::new(
+                    vec!["A".to_string(), "B".to_string()],
+                    vec!["C".to_string()],
+                    money!(20, "USD"),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let summary = ledger.activity_summary();
+        assert_eq!(summary["A"], (money!(30, "USD"), money!(20, "USD")));
+        assert_eq!(summary["B"], (money!(10, "USD"), money!(40, "USD")));
+        assert_eq!(summary["C"], (money!(20, "USD"), money!(0, "USD")));
+    }
+
+    #[cfg(feature = "chrono")]
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn ledger_settle_between_only_settles_transactions_in_window() {
+        let at = |day: u32, hour: u32| date(2019, 6, day).and_hms_opt(hour, 0, 0).unwrap();
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction_at(transaction!("A", "B", (10, "USD")), at(1, 9))
+            .unwrap();
+        ledger
+            .add_transaction_at(transaction!("B", "C", (10, "USD")), at(1, 20))
+            .unwrap();
+        ledger
+            .add_transaction_at(transaction!("C", "A", (4, "USD")), at(3, 12))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("D", "A", (6, "USD")))
+            .unwrap();
+
+        assert_eq!(ledger.history()[0].timestamp(), Some(at(1, 9)));
+        assert_eq!(ledger.history()[0].date(), Some(date(2019, 6, 1)));
+        let balances = ledger.to_vector();
+        let payments = ledger.settle_between(at(1, 0), at(2, 0)).unwrap();
+        assert_eq!(payments, vec![transaction!("A", "C", (10, "USD"))]);
+        assert_eq!(ledger.settle_between(at(1, 0), at(2, 0)).unwrap(), payments);
+        assert_eq!(ledger.to_vector(), balances);
+
+        ledger.apply(&payments).unwrap();
+        assert_eq!(ledger.balance_of("A"), money!(10, "USD"));
+        assert_eq!(ledger.balance_of("B"), money!(0, "USD"));
+        assert_eq!(ledger.balance_of("C"), money!(-4, "USD"));
+        assert_eq!(ledger.balance_of("D"), money!(-6, "USD"));
+        assert_eq!(ledger.settle_between(at(4, 0), at(5, 0)).unwrap(), vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn ledger_balances_as_of_replays_history_up_to_date() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")).with_date(date(2019, 5, 20)))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (4, "USD")).with_date(date(2019, 6, 1)))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "A", (7, "USD")).with_date(date(2019, 6, 2)))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("A", "C", (1, "USD")))
+            .unwrap();
+
+        let balances = ledger.balances_as_of(date(2019, 6, 1));
+        assert_eq!(balances["A"], money!(-11, "USD"));
+        assert_eq!(balances["B"], money!(6, "USD"));
+        assert_eq!(balances["C"], money!(5, "USD"));
+    }
+
+    //
+    // Tab Tests
+    //
+    #[test]
+    fn tab_close_period_settles_and_resets() {
+        let mut tab = Tab::new();
+        tab.add_transaction(transaction!("A", "B", (20, "USD")))
+            .unwrap();
+        tab.add_transaction(transaction!("B", "C", (20, "USD")))
+            .unwrap();
+
+        assert_eq!(
+            tab.close_period().unwrap(),
+            vec![transaction!("A", "C", (20, "USD"))]
+        );
+        assert_eq!(tab.period(), 1);
+        assert!(tab.ledger().to_vector().is_empty());
+    }
+
+    #[test]
+    fn tab_carries_over_unpaid_payments() {
+        let mut tab = Tab::new();
+        tab.add_transaction(transaction!("A", "B", (20, "USD")))
+            .unwrap();
+        let unpaid = tab.close_period().unwrap();
+
+        tab.carry_over(unpaid).unwrap();
+        tab.add_transaction(transaction!("B", "C", (5, "USD")))
+            .unwrap();
+        let mut payments = tab.close_period().unwrap();
+        payments.sort();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "B", (15, "USD")),
+                transaction!("A", "C", (5, "USD")),
+            ]
+        );
     }
 
     //
@@ -461,7 +4630,7 @@ mod tests {
         )
         .unwrap();
         let mut ledger = Ledger::new();
-        ledger.add_multi_party_transaction(transaction);
+        ledger.add_multi_party_transaction(transaction).unwrap();
         let remaining = ledger
             .to_vector()
             .into_iter()
@@ -469,6 +4638,69 @@ mod tests {
         assert_eq!(remaining, money!(0, "USD"));
     }
 
+    #[test]
+    fn mptx_allocation_strategy_decides_who_pays_leftovers() {
+        let split = |allocation| {
+            let transaction = MultiPartyTransaction::new_weighted(
+                vec![
+                    ("A".to_string(), 3),
+                    ("B".to_string(), 1),
+                    ("C".to_string(), 1),
+                ],
+                vec![("D".to_string(), 1)],
+                money!("0.07", "USD"),
+            )
+            .unwrap();
+            let mut ledger = Ledger::new();
+            ledger
+                .add_multi_party_transaction(transaction.with_allocation(allocation))
+                .unwrap();
+            ["A", "B", "C"].map(|party| ledger.balance_of(party))
+        };
+        assert_eq!(
+            split(AllocationStrategy::default()),
+            [
+                money!("-0.04", "USD"),
+                money!("-0.01", "USD"),
+                money!("-0.02", "USD")
+            ]
+        );
+        assert_eq!(
+            split(AllocationStrategy::LargestRemainder),
+            [
+                money!("-0.04", "USD"),
+                money!("-0.02", "USD"),
+                money!("-0.01", "USD")
+            ]
+        );
+    }
+
+    #[test]
+    fn mptx_allocates_currencies_with_other_minor_units() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_multi_party_transaction(
+                MultiPartyTransaction::new(
+                    vec!["A".to_string(), "B".to_string(), "C".to_string()],
+                    vec!["D".to_string()],
+                    Money::from_string("10.500".to_string(), "BHD".to_string()).unwrap(),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        let mut balances = ledger.to_vector();
+        balances.sort();
+        let total = balances
+            .iter()
+            .fold(Money::from_minor(0, Currency::get(BHD)), |acc, (_, b)| {
+                acc + b.clone()
+            });
+        assert!(total.is_zero());
+        assert_eq!(balances[0].1, Money::from_minor(-3500, Currency::get(BHD)));
+        assert_eq!(balances[2].1, Money::from_minor(-3500, Currency::get(BHD)));
+        assert_eq!(balances[3].1, Money::from_minor(10500, Currency::get(BHD)));
+    }
+
     #[test]
     fn mptx_can_handle_creditor_rounding() {
         let transaction = MultiPartyTransaction::new(
@@ -478,7 +4710,7 @@ mod tests {
         )
         .unwrap();
         let mut ledger = Ledger::new();
-        ledger.add_multi_party_transaction(transaction);
+        ledger.add_multi_party_transaction(transaction).unwrap();
         let ledger_balance = ledger
             .to_vector()
             .into_iter()
@@ -486,10 +4718,194 @@ mod tests {
         assert_eq!(ledger_balance, money!(0, "USD"));
     }
 
+    #[test]
+    fn mptx_rounding_drift_measures_distance_from_exact_split() {
+        let transaction = MultiPartyTransaction::new(
+            vec!["A".to_string(), "B".to_string(), "C".to_string()],
+            vec!["D".to_string()],
+            money!(10, "USD"),
+        )
+        .unwrap();
+        let mut ledger = Ledger::new();
+        ledger.add_multi_party_transaction(transaction).unwrap();
+
+        let drift = ledger.rounding_drift();
+        let usd = Currency::get(USD);
+        let epsilon = Money::from_decimal(Decimal::new(1, 4), usd);
+        assert!(drift["A"].approx_eq(
+            &Money::from_decimal(Decimal::new(33, 4), usd),
+            epsilon.clone()
+        ));
+        assert!(drift["B"].approx_eq(
+            &Money::from_decimal(Decimal::new(33, 4), usd),
+            epsilon.clone()
+        ));
+        assert!(drift["C"].approx_eq(&Money::from_decimal(Decimal::new(-67, 4), usd), epsilon));
+        assert!(drift["D"].is_zero());
+    }
+
+    #[test]
+    fn mptx_weighted_splits_by_percentage() {
+        let rent = MultiPartyTransaction::new_weighted(
+            vec![("A".to_string(), 60), ("B".to_string(), 40)],
+            vec![("C".to_string(), 1)],
+            money!(100, "USD"),
+        )
+        .unwrap();
+        let mut ledger = Ledger::new();
+        ledger.add_multi_party_transaction(rent).unwrap();
+
+        assert_eq!(ledger.balance_of("A"), money!(-60, "USD"));
+        assert_eq!(ledger.balance_of("B"), money!(-40, "USD"));
+        assert_eq!(ledger.balance_of("C"), money!(100, "USD"));
+    }
+
+    #[test]
+    fn mptx_with_shares_splits_like_new_weighted() {
+        let debtors = vec![("A".to_string(), 60), ("B".to_string(), 40)];
+        let creditors = vec![("C".to_string(), 1)];
+        let rent = MultiPartyTransaction::with_shares(
+            debtors.clone(),
+            creditors.clone(),
+            money!(100, "USD"),
+        )
+        .unwrap();
+        assert_eq!(rent.debtor_weights(), [60, 40]);
+        assert_eq!(
+            rent.debtor_shares(),
+            MultiPartyTransaction::new_weighted(debtors, creditors, money!(100, "USD"))
+                .unwrap()
+                .debtor_shares()
+        );
+        assert!(matches!(
+            MultiPartyTransaction::with_shares(
+                vec![("A".to_string(), 0)],
+                vec![("C".to_string(), 1)],
+                money!(100, "USD"),
+            ),
+            Err(TransactionError::InvalidWeight(_))
+        ));
+    }
+
+    #[test]
+    fn mptx_weighted_rejects_zero_and_negative_weights() {
+        for weight in &[0, -1] {
+            let transaction = MultiPartyTransaction::new_weighted(
+                vec![("A".to_string(), 1), ("B".to_string(), *weight)],
+                vec![("C".to_string(), 1)],
+                money!(10, "USD"),
+            );
+            match transaction {
+                Err(TransactionError::InvalidWeight(e)) => assert_eq!(e.party, "B"),
+                _ => panic!("expected an invalid weight error"),
+            }
+        }
+
+        let transaction = MultiPartyTransaction::new_weighted(
+            vec![("A".to_string(), 1)],
+            vec![("C".to_string(), 0)],
+            money!(10, "USD"),
+        );
+        assert!(matches!(
+            transaction,
+            Err(TransactionError::InvalidWeight(_))
+        ));
+    }
+
+    #[test]
+    fn mptx_rejects_empty_debtors_or_creditors() {
+        let transaction =
+            MultiPartyTransaction::new(vec![], vec!["C".to_string()], money!(10, "USD"));
+        match transaction {
+            Err(TransactionError::MissingField(e)) => assert_eq!(e.field, "debtors"),
+            _ => panic!("expected a missing field error"),
+        }
+
+        let transaction = MultiPartyTransaction::new_weighted(
+            vec![("A".to_string(), 1)],
+            vec![],
+            money!(10, "USD"),
+        );
+        match transaction {
+            Err(TransactionError::MissingField(e)) => assert_eq!(e.field, "creditors"),
+            _ => panic!("expected a missing field error"),
+        }
+    }
+
+    #[test]
+    fn mptx_rounding_drift_handles_weights_summing_past_i32() {
+        let transaction = MultiPartyTransaction::new_weighted(
+            vec![("A".to_string(), i32::MAX), ("B".to_string(), 1)],
+            vec![("C".to_string(), 1)],
+            money!(10, "USD"),
+        )
+        .unwrap();
+        let mut ledger = Ledger::new();
+        ledger.add_multi_party_transaction(transaction).unwrap();
+
+        let drift = ledger.rounding_drift();
+        let total = drift
+            .values()
+            .fold(money!(0, "USD"), |acc, x| acc + x.clone());
+        assert!(total.approx_eq(
+            &money!(0, "USD"),
+            Money::from_decimal(Decimal::new(1, 4), Currency::get(USD))
+        ));
+    }
+
+    #[test]
+    fn mptx_converts_to_and_from_simple_transactions() {
+        let lunch = transaction!("A", "B", (10, "USD"));
+        let multi = MultiPartyTransaction::from(lunch.clone());
+        assert_eq!(multi.debtors(), ["A".to_string()]);
+        assert_eq!(multi.creditors(), ["B".to_string()]);
+        assert_eq!(multi.amount(), &money!(10, "USD"));
+        assert_eq!(multi.try_into_simple(), Some(lunch));
+
+        let dinner = MultiPartyTransaction::new(
+            vec!["A".to_string(), "B".to_string()],
+            vec!["C".to_string()],
+            money!(10, "USD"),
+        )
+        .unwrap();
+        assert_eq!(dinner.try_into_simple(), None);
+    }
+
+    #[test]
+    fn mptx_can_be_added_in_bulk() {
+        let dinner = MultiPartyTransaction::new(
+            vec!["A".to_string(), "B".to_string()],
+            vec!["C".to_string()],
+            money!(10, "USD"),
+        )
+        .unwrap();
+        let taxi = MultiPartyTransaction::new(
+            vec!["C".to_string()],
+            vec!["A".to_string(), "B".to_string()],
+            money!(4, "USD"),
+        )
+        .unwrap();
+        let mut ledger = Ledger::new();
+        ledger
+            .add_multi_party_transactions(vec![dinner, taxi])
+            .unwrap();
+        let mut balances = ledger.to_vector();
+        balances.sort();
+        assert_eq!(
+            balances,
+            vec![
+                ("A".to_string(), money!(-3, "USD")),
+                ("B".to_string(), money!(-3, "USD")),
+                ("C".to_string(), money!(6, "USD")),
+            ]
+        );
+    }
+
     //
     // Transaction Tests
     //
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn tx_can_create_positive_transaction() {
         match Transaction::new("A".to_string(), "B".to_string(), money!(1, "USD")) {
             Ok(_) => assert!(true),
@@ -498,6 +4914,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn tx_cannot_create_non_positive_transaction() {
         match Transaction::new("A".to_string(), "B".to_string(), money!(-1, "USD")) {
             Ok(_) => assert!(false),
@@ -509,4 +4926,198 @@ mod tests {
             Err(_) => assert!(true),
         };
     }
+
+    #[test]
+    fn tx_from_tuple_rejects_non_positive_amounts() {
+        let zero = Transaction::from_tuple("A".to_string(), "B".to_string(), (0, "USD"));
+        match zero.unwrap_err() {
+            TransactionError::InvalidAmount(e) => assert_eq!(e.amount, money!(0, "USD")),
+            e => panic!("unexpected error {}", e),
+        }
+
+        let negative = Transaction::from_tuple("A".to_string(), "B".to_string(), (-5, "USD"));
+        match negative.unwrap_err() {
+            TransactionError::InvalidAmount(e) => assert_eq!(e.amount, money!(-5, "USD")),
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn tx_from_tuple_accepts_decimal_strings() {
+        let transaction = transaction!("A", "B", ("10.50", "USD"));
+        assert_eq!(
+            transaction,
+            Transaction::new("A".to_string(), "B".to_string(), money!("10.50", "USD")).unwrap()
+        );
+        assert!(matches!(
+            Transaction::from_tuple("A".to_string(), "B".to_string(), ("-1.50", "USD")),
+            Err(TransactionError::InvalidAmount(_))
+        ));
+        assert!(matches!(
+            Transaction::from_tuple("A".to_string(), "B".to_string(), ("1.2.3", "USD")),
+            Err(TransactionError::InvalidAmountString(_))
+        ));
+    }
+
+    #[test]
+    fn tx_from_tuple_supports_any_iso_currency() {
+        let yen = transaction!("A", "B", (500, "JPY"));
+        assert_eq!(yen.amount, Money::from_minor(500, Currency::get(JPY)));
+        assert_eq!(yen.to_string(), "A owes B ¥500");
+
+        let dinar = transaction!("A", "B", (5, "BHD"));
+        assert_eq!(dinar.amount, Money::from_minor(5000, Currency::get(BHD)));
+
+        let unknown = Transaction::from_tuple("A".to_string(), "B".to_string(), (5, "XYZ"));
+        match unknown.unwrap_err() {
+            TransactionError::UnknownCurrency(e) => assert_eq!(e.code, "XYZ"),
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn tx_from_tuple_normalizes_currency_codes() {
+        assert_eq!(
+            transaction!("A", "B", (1, " Gbp ")),
+            transaction!("A", "B", (1, "GBP"))
+        );
+    }
+
+    #[test]
+    fn tx_cannot_create_transaction_with_same_party() {
+        let result = Transaction::new("A".to_string(), "A".to_string(), money!(10, "USD"));
+        assert!(matches!(result, Err(TransactionError::SameParty(_))));
+        assert!(Transaction::new("A".to_string(), "a".to_string(), money!(10, "USD")).is_ok());
+    }
+
+    #[test]
+    fn mptx_cannot_list_party_as_debtor_and_creditor() {
+        let result = MultiPartyTransaction::new(
+            vec!["A".to_string(), "B".to_string()],
+            vec!["C".to_string(), "B".to_string()],
+            money!(10, "USD"),
+        );
+        match result {
+            Err(TransactionError::SameParty(e)) => assert_eq!(e.party, "B"),
+            _ => panic!("expected a same party error"),
+        }
+    }
+
+    #[test]
+    fn tx_cannot_create_transaction_with_empty_party() {
+        for name in &["", "   "] {
+            let debtor = Transaction::new(name.to_string(), "B".to_string(), money!(1, "USD"));
+            assert!(matches!(debtor, Err(TransactionError::InvalidParty(_))));
+
+            let creditor = Transaction::new("A".to_string(), name.to_string(), money!(1, "USD"));
+            assert!(matches!(creditor, Err(TransactionError::InvalidParty(_))));
+        }
+    }
+
+    #[test]
+    fn tx_builder_parses_decimal_amounts() {
+        let transaction = Transaction::builder()
+            .debtor("Alice")
+            .creditor("Bob")
+            .amount_str("10.50", "usd")
+            .build()
+            .unwrap();
+        assert_eq!(
+            transaction,
+            Transaction::new(
+                "Alice".to_string(),
+                "Bob".to_string(),
+                money!("10.50", "USD")
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn tx_builder_reports_invalid_fields() {
+        let builder = || Transaction::builder().debtor("Alice").creditor("Bob");
+        assert!(matches!(
+            builder().build(),
+            Err(TransactionError::MissingField(_))
+        ));
+        assert!(matches!(
+            builder().amount_str("ten", "USD").build(),
+            Err(TransactionError::InvalidAmountString(_))
+        ));
+        assert!(matches!(
+            builder().amount_str("10", "XYZ").build(),
+            Err(TransactionError::UnknownCurrency(_))
+        ));
+        assert!(matches!(
+            builder().amount_str("-10", "USD").build(),
+            Err(TransactionError::InvalidAmount(_))
+        ));
+        let same_party = Transaction::builder()
+            .debtor("Alice")
+            .creditor("Alice")
+            .amount(money!(10, "USD"))
+            .build();
+        match same_party {
+            Err(e @ TransactionError::SameParty(_)) => {
+                assert_eq!(e.to_string(), "Alice can't owe themselves")
+            }
+            _ => panic!("expected a same party error"),
+        }
+    }
+
+    #[test]
+    fn tx_displays_amount_with_minor_units() {
+        let transaction = Transaction::new(
+            "A".to_string(),
+            "B".to_string(),
+            Money::from_major(10, Currency::get(Iso::USD)),
+        )
+        .unwrap();
+        assert_eq!(transaction.to_string(), "A owes B $10.00");
+    }
+
+    #[test]
+    fn tx_displays_memo_when_present() {
+        let plain = transaction!("A", "B", (10, "USD"));
+        assert_eq!(plain.memo(), None);
+        assert_eq!(plain.to_string(), "A owes B $10.00");
+
+        let dinner = plain.with_memo("Dinner at Luigi's".to_string());
+        assert_eq!(dinner.memo(), Some("Dinner at Luigi's"));
+        assert_eq!(dinner.to_string(), "A owes B $10.00 (Dinner at Luigi's)");
+    }
+
+    #[test]
+    fn ledger_settlement_payments_have_no_memo() {
+        let mut ledger = Ledger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")).with_memo("Lunch".to_string()))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (10, "USD")).with_memo("Taxi".to_string()))
+            .unwrap();
+
+        let payments = ledger.settle().unwrap();
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].memo(), None);
+        assert_eq!(ledger.history()[0].memo(), Some("Lunch"));
+    }
+
+    #[test]
+    fn tx_can_convert_currency() {
+        let transaction = transaction!("A", "B", (10, "USD"));
+        let converted = transaction
+            .convert(Currency::get(GBP), Decimal::new(7777, 4))
+            .unwrap();
+        let expected = Transaction::new("A".to_string(), "B".to_string(), money!("7.78", "GBP"));
+        assert_eq!(converted, expected.unwrap());
+    }
+
+    #[test]
+    fn tx_convert_rejects_non_positive_results() {
+        let transaction = transaction!("A", "B", (10, "USD"));
+        assert!(transaction
+            .convert(Currency::get(GBP), Decimal::new(1, 4))
+            .is_err());
+    }
 }
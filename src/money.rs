@@ -0,0 +1,867 @@
+//! Helpers for working with rusty_money's `Money` that debtsolver relies on but which
+//! rusty_money does not provide itself.
+//!
+//! Amounts stored as integer minor units (e.g. 2999 cents) can be created with
+//! `Money::from_minor` and converted back with `MoneyExt::to_minor_units`.
+//!
+//! Basic arithmetic, such as adding two amounts or multiplying an amount by an integer count
+//! (`money!(12, "USD") * 3`), is implemented by rusty_money and works on `Money` directly. Its
+//! operators keep every decimal place of the result, so use `MoneyExt::mul_rounded` and
+//! `MoneyExt::div_truncated` when the result has to be a payable amount.
+//!
+//! Currencies outside ISO-4217, such as points in a game, can be added with `register_currency`,
+//! after which they work like any other currency.
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use rusty_money::{
+    Currency, Formatter, LocalFormat, Locale, Money, MoneyError, Params, Position, Round,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+/// The longest amount string, in characters, that will be parsed. This is far longer than any
+/// real amount needs, since rusty_money only accepts amounts whose whole and fractional parts each
+/// fit in an i32.
+pub const MAX_AMOUNT_LENGTH: usize = 32;
+
+/// Errors that can occur while parsing an amount and currency with `MoneyExt::from_string_checked`
+/// or `MoneyExt::parse`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MoneyParseError {
+    /// The amount has a character that isn't a digit or separator, a sign anywhere but the start,
+    /// or more digits than can be parsed.
+    InvalidDigit,
+    /// The amount has more than one decimal separator, such as "1.2.3".
+    MultipleDelimiters,
+    /// The amount is empty or only whitespace.
+    EmptyAmount,
+    /// The currency is missing, or isn't in ISO-4217 and wasn't added with `register_currency`.
+    UnknownCurrency,
+}
+
+impl Error for MoneyParseError {}
+
+impl fmt::Display for MoneyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyParseError::InvalidDigit => write!(f, "Amount has an invalid digit"),
+            MoneyParseError::MultipleDelimiters => {
+                write!(f, "Amount has more than one decimal separator")
+            }
+            MoneyParseError::EmptyAmount => write!(f, "Amount is empty"),
+            MoneyParseError::UnknownCurrency => write!(f, "Currency is unknown"),
+        }
+    }
+}
+
+/// Decides who receives the units left over when an amount can't be split exactly by weight.
+///
+/// Shares are whole minor units of the currency, such as cents, so after each share is rounded down
+/// there are usually a few minor units left over, plus any fraction of a minor unit in the amount.
+/// Each strategy hands the leftover units out one at a time, and gives the fraction to the first
+/// party it picks.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AllocationStrategy {
+    /// Leftovers go to the parties in the order they are listed, like `Money::allocate`.
+    RoundRobin,
+    /// Leftovers go to the parties whose exact share was rounded down the most, which is the
+    /// fairest split (Hamilton's method). Ties go to the party listed first.
+    LargestRemainder,
+    /// Leftovers go to the parties in reverse order, starting with the last one listed.
+    #[default]
+    FavorLast,
+}
+
+/// The most minor units a registered currency can have. Amounts are converted to whole minor
+/// units as an i64 when they're allocated, which leaves room for 18 decimal places.
+pub const MAX_MINOR_UNITS: u32 = 18;
+
+/// Returns the currency for an ISO-4217 code, or a code added with `register_currency`, ignoring
+/// case and surrounding whitespace, so that "usd" and " Gbp " resolve like "USD" and "GBP".
+pub fn find_currency(code: &str) -> Result<&'static Currency, MoneyError> {
+    let code = code.trim().to_uppercase();
+    Currency::find(&code).or_else(|e| match custom_currencies().lock().unwrap().get(&code) {
+        Some(currency) => Ok(*currency),
+        None => Err(e),
+    })
+}
+
+/// Registers a currency that isn't in ISO-4217, such as house points or in-game gold, so it can
+/// be used for `Money`, transactions and ledgers like any other. The code is uppercased, and the
+/// currency is formatted like US dollars but with the symbol after the amount.
+///
+/// Registering a code again with the same minor units and symbol returns the same currency.
+/// Returns `MoneyError::InvalidCurrency` if the code is empty, isn't alphabetic, is an ISO-4217
+/// code or was already registered differently, or if there are more than `MAX_MINOR_UNITS`
+/// minor units.
+pub fn register_currency(
+    code: &str,
+    minor_units: u32,
+    symbol: &str,
+) -> Result<&'static Currency, MoneyError> {
+    let code = code.trim().to_uppercase();
+    let valid_code = !code.is_empty() && code.chars().all(char::is_alphabetic);
+    if !valid_code || minor_units > MAX_MINOR_UNITS || Currency::find(&code).is_ok() {
+        return Err(MoneyError::InvalidCurrency);
+    }
+    let mut currencies = custom_currencies().lock().unwrap();
+    if let Some(existing) = currencies.get(&code) {
+        if existing.exponent == minor_units && existing.symbol == symbol {
+            return Ok(*existing);
+        }
+        return Err(MoneyError::InvalidCurrency);
+    }
+    // Money holds &'static Currency, so registered currencies live for the rest of the program.
+    let code: &'static str = Box::leak(code.into_boxed_str());
+    let currency: &'static Currency = Box::leak(Box::new(Currency {
+        locale: Locale::EnUs,
+        exponent: minor_units,
+        iso_alpha_code: code,
+        iso_numeric_code: "",
+        name: code,
+        symbol: Box::leak(symbol.to_string().into_boxed_str()),
+        symbol_first: false,
+        minor_denomination: 1,
+    }));
+    currencies.insert(code.to_string(), currency);
+    Ok(currency)
+}
+
+fn custom_currencies() -> &'static Mutex<HashMap<String, &'static Currency>> {
+    static CURRENCIES: OnceLock<Mutex<HashMap<String, &'static Currency>>> = OnceLock::new();
+    CURRENCIES.get_or_init(Default::default)
+}
+
+/// Extends `Money` with operations that are useful when tracking and settling debts.
+pub trait MoneyExt {
+    /// Returns true if the absolute difference between the two amounts is within epsilon.
+    ///
+    /// Amounts in different currencies are never approximately equal.
+    fn approx_eq(&self, other: &Money, epsilon: Money) -> bool;
+
+    /// Compares two amounts like `Ord::cmp`, but returns None instead of panicking when they're in
+    /// different currencies, so mixed lists can be compared or sorted safely.
+    fn try_cmp(&self, other: &Money) -> Option<Ordering>;
+
+    /// Adds up the amounts, starting from zero in the given currency so that nothing sums to
+    /// zero. `Sum` can't be implemented for `Money` outside rusty_money, so use this in its place:
+    /// `Money::try_sum(usd, amounts.iter().cloned())`.
+    ///
+    /// Returns `MoneyError::InvalidCurrency` if any amount is in another currency.
+    fn try_sum<I: IntoIterator<Item = Money>>(
+        currency: &'static Currency,
+        amounts: I,
+    ) -> Result<Money, MoneyError>;
+
+    /// Creates a Money object from an amount string and a currency string like `Money::from_string`,
+    /// but rejects amounts longer than `MAX_AMOUNT_LENGTH`, or with a sign anywhere but the start,
+    /// with `MoneyParseError::InvalidDigit` before parsing them. `Money::from_string` can overflow
+    /// the stack or panic on such input, so use this for amounts from untrusted input.
+    ///
+    /// The error says what was wrong with the input, see `MoneyParseError`.
+    fn from_string_checked(amount: String, currency: String) -> Result<Money, MoneyParseError>;
+
+    /// Creates a Money object from an amount string and a currency string, rounding the amount
+    /// to the currency's minor units with the given strategy.
+    ///
+    /// `Money::from_string` keeps the amount exactly as written, and remains the default.
+    fn from_string_with_rounding(
+        amount: String,
+        currency: String,
+        mode: Round,
+    ) -> Result<Money, MoneyParseError>;
+
+    /// Creates a Money object from a single string holding an amount followed by a currency code,
+    /// separated by whitespace (e.g. "50 GBP").
+    ///
+    /// Returns `MoneyParseError::EmptyAmount` for an empty string, and
+    /// `MoneyParseError::UnknownCurrency` if the currency is missing or unknown, including when
+    /// the string has extra parts after it. Amounts are checked like `from_string_checked`.
+    fn parse(input: &str) -> Result<Money, MoneyParseError>;
+
+    /// Multiplies the amount by a factor, rounding the result to the currency's minor units with
+    /// bankers rounding (e.g. 10.00 USD * 1.5 = 15.00 USD).
+    fn mul_rounded<T: Into<Decimal>>(&self, factor: T) -> Money;
+
+    /// Divides the amount by a divisor, discarding any remainder smaller than the currency's minor
+    /// units (e.g. 10.00 USD / 3 = 3.33 USD). Results are truncated towards zero, so negative
+    /// amounts are treated like positive ones.
+    ///
+    /// Panics if the divisor is zero.
+    fn div_truncated<T: Into<Decimal>>(&self, divisor: T) -> Money;
+
+    /// Returns the amount with its sign flipped. Zero stays zero, rather than becoming -0.
+    fn negated(&self) -> Money;
+
+    /// Returns the amount without its sign, e.g. to show a debtor's balance as what they owe.
+    fn abs(&self) -> Money;
+
+    /// Returns the amount as a whole number of the currency's minor units, e.g. 2999 for 29.99 USD
+    /// or 500 for 500 JPY. Fractions of a minor unit are rounded with bankers rounding.
+    ///
+    /// Panics if the amount has too many minor units to fit in an i64.
+    fn to_minor_units(&self) -> i64;
+
+    /// Splits the amount into shares proportional to the ratios, handing out whatever is left
+    /// over after rounding each share down to whole minor units according to the strategy. The shares
+    /// always add up to the amount.
+    ///
+    /// Panics if the ratios are empty, or don't add up to a positive number.
+    fn allocate_with(&self, ratios: &[i32], strategy: AllocationStrategy) -> Vec<Money>;
+
+    /// Splits the amount into `parts` equal shares of whole minor units, and returns them with
+    /// whatever couldn't be shared out, rather than handing it to someone like `allocate_with`.
+    /// Splitting 10 USD three ways gives three shares of 3.33 and a remainder of 0.01. Shares are
+    /// rounded toward zero, so the remainder has the same sign as the amount.
+    ///
+    /// Panics if `parts` is zero.
+    fn split_evenly(&self, parts: usize) -> (Vec<Money>, Money);
+
+    /// Formats the amount with the currency's symbol and separators, always showing its minor
+    /// units, e.g. "$10.00", "£1,000.50" or "¥10". `Display` shows however many decimal places
+    /// the amount happens to have, so `Money::from_major(10, USD)` displays as "$10".
+    fn format_with_symbol(&self) -> String;
+
+    /// Formats the amount as a plain decimal followed by the currency code, always showing its
+    /// minor units, e.g. "10.00 USD", "-1000.50 GBP" or "10 JPY".
+    fn format_with_code(&self) -> String;
+
+    /// Formats the amount like `format_with_code`, but with its digits grouped using the
+    /// separators of the currency's locale, e.g. "1,000,000.00 USD" or "-1.000,50 EUR".
+    fn format_grouped(&self) -> String;
+}
+
+impl MoneyExt for Money {
+    fn approx_eq(&self, other: &Money, epsilon: Money) -> bool {
+        if self.currency() != other.currency() || self.currency() != epsilon.currency() {
+            return false;
+        }
+        let difference = (*self.amount() - *other.amount()).abs();
+        difference <= epsilon.amount().abs()
+    }
+
+    fn try_cmp(&self, other: &Money) -> Option<Ordering> {
+        if self.currency() != other.currency() {
+            return None;
+        }
+        Some(self.amount().cmp(other.amount()))
+    }
+
+    fn try_sum<I: IntoIterator<Item = Money>>(
+        currency: &'static Currency,
+        amounts: I,
+    ) -> Result<Money, MoneyError> {
+        let mut total = Money::from_major(0, currency);
+        for amount in amounts {
+            if amount.currency() != currency {
+                return Err(MoneyError::InvalidCurrency);
+            }
+            total += amount;
+        }
+        Ok(total)
+    }
+
+    fn from_string_checked(amount: String, currency: String) -> Result<Money, MoneyParseError> {
+        if amount.trim().is_empty() {
+            return Err(MoneyParseError::EmptyAmount);
+        }
+        if amount.chars().count() > MAX_AMOUNT_LENGTH {
+            return Err(MoneyParseError::InvalidDigit);
+        }
+        if amount.chars().skip(1).any(|c| c == '-' || c == '+') {
+            return Err(MoneyParseError::InvalidDigit);
+        }
+        let currency = find_currency(&currency).map_err(|_| MoneyParseError::UnknownCurrency)?;
+        let separator = LocalFormat::from_locale(currency.locale).exponent_separator;
+        if amount.matches(separator).count() > 1 {
+            return Err(MoneyParseError::MultipleDelimiters);
+        }
+        // Any other amount rusty_money can't parse has a stray character or too many digits.
+        if Currency::find(currency.iso_alpha_code).is_ok() {
+            return Money::from_string(amount, currency.iso_alpha_code.to_string())
+                .map_err(|_| MoneyParseError::InvalidDigit);
+        }
+        // rusty_money can only parse ISO currencies, so parse registered ones as US dollars, which
+        // share their separators, and rescale whole amounts to the currency's minor units.
+        let has_fraction = amount.contains('.');
+        let mut parsed = *Money::from_string(amount, "USD".to_string())
+            .map_err(|_| MoneyParseError::InvalidDigit)?
+            .amount();
+        if !has_fraction {
+            parsed.rescale(currency.exponent);
+        }
+        Ok(Money::from_decimal(parsed, currency))
+    }
+
+    fn from_string_with_rounding(
+        amount: String,
+        currency: String,
+        mode: Round,
+    ) -> Result<Money, MoneyParseError> {
+        let money = Money::from_string_checked(amount, currency)?;
+        Ok(money.round(money.currency().exponent, mode))
+    }
+
+    fn parse(input: &str) -> Result<Money, MoneyParseError> {
+        let input = input.trim();
+        let (amount, currency) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        Money::from_string_checked(amount.to_string(), currency.to_string())
+    }
+
+    fn mul_rounded<T: Into<Decimal>>(&self, factor: T) -> Money {
+        let product = self.clone() * factor.into();
+        product.round(self.currency().exponent, Round::HalfEven)
+    }
+
+    fn div_truncated<T: Into<Decimal>>(&self, divisor: T) -> Money {
+        let quotient = *self.amount() / divisor.into();
+        Money::from_decimal(
+            quotient.round_dp_with_strategy(self.currency().exponent, RoundingStrategy::ToZero),
+            self.currency(),
+        )
+    }
+
+    fn negated(&self) -> Money {
+        if self.is_zero() {
+            return Money::from_decimal(Decimal::new(0, self.amount().scale()), self.currency());
+        }
+        Money::from_decimal(-*self.amount(), self.currency())
+    }
+
+    fn abs(&self) -> Money {
+        Money::from_decimal(self.amount().abs(), self.currency())
+    }
+
+    fn to_minor_units(&self) -> i64 {
+        let exponent = self.currency().exponent;
+        let rounded = self.round(exponent, Round::HalfEven);
+        (*rounded.amount() * Decimal::from(10i64.pow(exponent)))
+            .to_i64()
+            .expect("amount has too many minor units to fit in an i64")
+    }
+
+    fn allocate_with(&self, ratios: &[i32], strategy: AllocationStrategy) -> Vec<Money> {
+        let total = Decimal::from(ratios.iter().map(|&r| i64::from(r)).sum::<i64>());
+        assert!(
+            total.is_sign_positive() && !total.is_zero(),
+            "ratios must add up to more than 0"
+        );
+        let scale = Decimal::from(10i64.pow(self.currency().exponent));
+        let minor_units = *self.amount() * scale;
+        let whole = minor_units.trunc();
+        let exact: Vec<Decimal> = ratios
+            .iter()
+            .map(|&ratio| whole * Decimal::from(ratio) / total)
+            .collect();
+        let mut shares: Vec<Decimal> = exact.iter().map(Decimal::floor).collect();
+        let leftover = (whole - shares.iter().sum::<Decimal>())
+            .to_usize()
+            .unwrap_or(0);
+
+        let mut order: Vec<usize> = (0..ratios.len()).collect();
+        match strategy {
+            AllocationStrategy::RoundRobin => {}
+            AllocationStrategy::LargestRemainder => {
+                order.sort_by_key(|&i| Reverse(exact[i] - shares[i]));
+            }
+            AllocationStrategy::FavorLast => order.reverse(),
+        }
+        for &i in order.iter().take(leftover) {
+            shares[i] += Decimal::ONE;
+        }
+        shares[order[0]] += minor_units - whole;
+        shares
+            .into_iter()
+            .map(|share| Money::from_decimal(share / scale, self.currency()))
+            .collect()
+    }
+
+    fn split_evenly(&self, parts: usize) -> (Vec<Money>, Money) {
+        assert!(parts > 0, "can't split an amount into zero parts");
+        let scale = Decimal::from(10i64.pow(self.currency().exponent));
+        let units = (*self.amount() * scale / Decimal::from(parts)).trunc();
+        let share = Money::from_decimal(units / scale, self.currency());
+        let remainder = self.clone() - share.clone() * Decimal::from(parts);
+        (vec![share; parts], remainder)
+    }
+
+    fn format_with_symbol(&self) -> String {
+        with_minor_units(self).to_string()
+    }
+
+    fn format_with_code(&self) -> String {
+        format!(
+            "{} {}",
+            with_minor_units(self).amount(),
+            self.currency().iso_alpha_code
+        )
+    }
+
+    fn format_grouped(&self) -> String {
+        let format = LocalFormat::from_locale(self.currency().locale);
+        let params = Params {
+            digit_separator: format.digit_separator,
+            exponent_separator: format.exponent_separator,
+            separator_pattern: format.digit_separator_pattern(),
+            positions: vec![
+                Position::Sign,
+                Position::Amount,
+                Position::Space,
+                Position::Code,
+            ],
+            code: Some(self.currency().iso_alpha_code),
+            ..Default::default()
+        };
+        Formatter::money(&with_minor_units(self), params)
+    }
+}
+
+// Rounds the amount to the currency's minor units, and pads it with zeros to show all of them.
+fn with_minor_units(money: &Money) -> Money {
+    let exponent = money.currency().exponent;
+    let mut amount = *money.round(exponent, Round::HalfEven).amount();
+    amount.rescale(exponent);
+    Money::from_decimal(amount, money.currency())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusty_money::money;
+    use rusty_money::Iso::*;
+
+    #[test]
+    fn find_currency_normalizes_codes() {
+        for code in &["usd", "USD", "Usd"] {
+            assert_eq!(find_currency(code), Ok(Currency::get(USD)));
+        }
+        assert_eq!(find_currency(" Gbp "), Ok(Currency::get(GBP)));
+        assert_eq!(find_currency("XYZ"), Err(MoneyError::InvalidCurrency));
+        assert_eq!(find_currency(""), Err(MoneyError::InvalidCurrency));
+    }
+
+    #[test]
+    fn registered_currencies_work_like_iso_ones() {
+        let points = register_currency("pts", 0, "pts").unwrap();
+        assert_eq!(register_currency("PTS", 0, "pts"), Ok(points));
+        assert_eq!(find_currency(" Pts "), Ok(points));
+        assert_eq!(
+            register_currency("PTS", 2, "pts"),
+            Err(MoneyError::InvalidCurrency)
+        );
+        assert_eq!(
+            register_currency("USD", 2, "$"),
+            Err(MoneyError::InvalidCurrency)
+        );
+        assert_eq!(
+            register_currency("G0LD", 2, "g"),
+            Err(MoneyError::InvalidCurrency)
+        );
+
+        let ten = Money::from_string_checked("10".to_string(), "PTS".to_string()).unwrap();
+        assert_eq!(ten, Money::from_minor(10, points));
+        assert_eq!(ten.format_with_code(), "10 PTS");
+        assert_eq!(
+            ten.allocate_with(&[1, 1, 1], AllocationStrategy::RoundRobin),
+            vec![
+                Money::from_major(4, points),
+                Money::from_major(3, points),
+                Money::from_major(3, points)
+            ]
+        );
+        assert!(Money::from_string_checked("1.5".to_string(), "PTS".to_string()).is_ok());
+
+        let gold = register_currency("GOLD", 3, "g").unwrap();
+        let coins = Money::from_string_checked("1,000.5".to_string(), "gold".to_string()).unwrap();
+        assert_eq!(coins.to_minor_units(), 1_000_500);
+        assert_eq!(coins.format_grouped(), "1,000.500 GOLD");
+        assert_eq!(coins.currency(), gold);
+    }
+
+    #[test]
+    fn money_try_cmp_refuses_other_currencies() {
+        assert_eq!(
+            money!(10, "USD").try_cmp(&money!("9.99", "USD")),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            money!(10, "USD").try_cmp(&money!("10.00", "USD")),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(money!(10, "USD").try_cmp(&money!(10, "GBP")), None);
+
+        let mut amounts = vec![money!(5, "GBP"), money!(2, "USD"), money!(1, "GBP")];
+        amounts.sort_by(|a, b| {
+            a.currency()
+                .iso_alpha_code
+                .cmp(b.currency().iso_alpha_code)
+                .then_with(|| a.try_cmp(b).unwrap())
+        });
+        assert_eq!(
+            amounts,
+            vec![money!(1, "GBP"), money!(5, "GBP"), money!(2, "USD")]
+        );
+    }
+
+    #[test]
+    fn money_try_sum_adds_amounts_in_one_currency() {
+        let usd = Currency::get(USD);
+        let amounts = vec![
+            money!(10, "USD"),
+            money!("2.50", "USD"),
+            money!("0.01", "USD"),
+        ];
+        assert_eq!(Money::try_sum(usd, amounts), Ok(money!("12.51", "USD")));
+        assert_eq!(Money::try_sum(usd, Vec::new()), Ok(money!(0, "USD")));
+        assert_eq!(
+            Money::try_sum(usd, vec![money!(10, "USD"), money!(10, "GBP")]),
+            Err(MoneyError::InvalidCurrency)
+        );
+    }
+
+    #[test]
+    fn money_approx_eq_within_tolerance() {
+        let observed = money!("10.01", "USD");
+        assert!(observed.approx_eq(&money!(10, "USD"), money!("0.01", "USD")));
+        assert!(money!(10, "USD").approx_eq(&observed, money!("0.01", "USD")));
+    }
+
+    #[test]
+    fn money_approx_eq_outside_tolerance() {
+        let observed = money!("10.02", "USD");
+        assert!(!observed.approx_eq(&money!(10, "USD"), money!("0.01", "USD")));
+        assert!(!money!(10, "USD").approx_eq(&money!(10, "GBP"), money!("0.01", "USD")));
+    }
+
+    #[test]
+    fn money_approx_eq_with_zero_tolerance_is_exact() {
+        let (a, b) = (money!("10.01", "USD"), money!(10, "USD"));
+        assert!(a.approx_eq(&b, money!("0.01", "USD")));
+        assert!(!a.approx_eq(&b, money!(0, "USD")));
+        assert!(b.approx_eq(&money!("10.00", "USD"), money!(0, "USD")));
+    }
+
+    #[test]
+    fn money_multiplies_by_integer_count() {
+        for (count, expected) in [(0i64, money!(0, "USD")), (3, money!(36, "USD"))] {
+            assert_eq!(money!(12, "USD") * count, expected);
+            assert_eq!(count * money!(12, "USD"), expected);
+        }
+        assert_eq!(money!("12.34", "USD") * 3i64, money!("37.02", "USD"));
+    }
+
+    #[test]
+    fn money_mul_rounded_rounds_to_minor_units() {
+        assert_eq!(
+            money!(10, "USD").mul_rounded(Decimal::new(15, 1)),
+            money!(15, "USD")
+        );
+        assert_eq!(
+            money!("12.34", "USD").mul_rounded(3),
+            money!("37.02", "USD")
+        );
+        // A 15% tip on 10.05 is 1.5075, which rounds to the nearest cent.
+        assert_eq!(
+            money!("10.05", "USD").mul_rounded(Decimal::new(15, 2)),
+            money!("1.51", "USD")
+        );
+        assert_eq!(
+            money!(1000, "JPY").mul_rounded(Decimal::new(333, 3)),
+            money!(333, "JPY")
+        );
+    }
+
+    #[test]
+    fn money_div_truncated_discards_remainder() {
+        assert_eq!(money!(10, "USD").div_truncated(3), money!("3.33", "USD"));
+        assert_eq!(money!(20, "USD").div_truncated(3), money!("6.66", "USD"));
+        assert_eq!(money!(-20, "USD").div_truncated(3), money!("-6.66", "USD"));
+        assert_eq!(
+            money!(10, "USD").div_truncated(Decimal::new(4, 0)),
+            money!("2.50", "USD")
+        );
+    }
+
+    #[test]
+    fn money_negated_flips_sign() {
+        assert_eq!(money!(10, "USD").negated(), money!(-10, "USD"));
+        assert_eq!(money!("-2.50", "USD").negated(), money!("2.50", "USD"));
+
+        let zero = money!(0, "USD").negated();
+        assert!(zero.is_zero());
+        assert!(zero.amount().is_sign_positive());
+        assert_eq!(zero.to_string(), money!(0, "USD").to_string());
+    }
+
+    #[test]
+    fn money_abs_drops_sign() {
+        assert_eq!(money!("-12.34", "USD").abs(), money!("12.34", "USD"));
+        assert_eq!(money!(5, "GBP").abs(), money!(5, "GBP"));
+    }
+
+    #[test]
+    fn money_round_trips_through_minor_units() {
+        let dollars = Money::from_minor(2999, Currency::get(USD));
+        assert_eq!(dollars, money!("29.99", "USD"));
+        assert_eq!(dollars.to_minor_units(), 2999);
+        assert_eq!(money!(-5, "USD").to_minor_units(), -500);
+        assert_eq!(money!("0.125", "USD").to_minor_units(), 12);
+
+        let yen = Money::from_minor(2999, Currency::get(JPY));
+        assert_eq!(yen, money!(2999, "JPY"));
+        assert_eq!(yen.to_minor_units(), 2999);
+    }
+
+    #[test]
+    fn money_allocate_with_hands_out_leftovers_by_strategy() {
+        let split = |strategy| money!("0.07", "USD").allocate_with(&[3, 1, 1], strategy);
+        let expected = |cents: [i64; 3]| {
+            cents
+                .iter()
+                .map(|&c| Money::from_minor(c, Currency::get(USD)))
+                .collect::<Vec<Money>>()
+        };
+        assert_eq!(split(AllocationStrategy::RoundRobin), expected([5, 1, 1]));
+        assert_eq!(
+            split(AllocationStrategy::LargestRemainder),
+            expected([4, 2, 1])
+        );
+        assert_eq!(split(AllocationStrategy::FavorLast), expected([4, 1, 2]));
+    }
+
+    #[test]
+    fn money_allocate_with_splits_minor_units() {
+        let shares =
+            money!("10.01", "USD").allocate_with(&[1, 1, 1], AllocationStrategy::RoundRobin);
+        assert_eq!(
+            shares,
+            vec![
+                money!("3.34", "USD"),
+                money!("3.34", "USD"),
+                money!("3.33", "USD")
+            ]
+        );
+
+        let usd = Currency::get(USD);
+        let shares = Money::from_decimal(Decimal::new(10015, 3), usd)
+            .allocate_with(&[1, 1, 1], AllocationStrategy::RoundRobin);
+        assert_eq!(
+            shares,
+            vec![
+                Money::from_decimal(Decimal::new(3345, 3), usd),
+                money!("3.34", "USD"),
+                money!("3.33", "USD")
+            ]
+        );
+    }
+
+    #[test]
+    fn money_split_evenly_returns_remainder() {
+        let (shares, remainder) = money!(10, "USD").split_evenly(3);
+        assert_eq!(shares, vec![money!("3.33", "USD"); 3]);
+        assert_eq!(remainder, money!("0.01", "USD"));
+
+        let (shares, remainder) = money!("-10", "USD").split_evenly(4);
+        assert_eq!(shares, vec![money!("-2.50", "USD"); 4]);
+        assert!(remainder.is_zero());
+
+        let (shares, remainder) = money!(1000, "JPY").split_evenly(7);
+        assert_eq!(shares, vec![money!(142, "JPY"); 7]);
+        assert_eq!(remainder, money!(6, "JPY"));
+    }
+
+    #[test]
+    fn money_allocate_with_never_loses_or_creates_money() {
+        let strategies = [
+            AllocationStrategy::RoundRobin,
+            AllocationStrategy::LargestRemainder,
+            AllocationStrategy::FavorLast,
+        ];
+        for currency in [Currency::get(USD), Currency::get(JPY), Currency::get(BHD)] {
+            for minor in (-2000..=2000).step_by(37) {
+                let amount = Money::from_minor(minor, currency);
+                for parties in 1..=7 {
+                    let ratios: Vec<i32> = (1..=parties).collect();
+                    for strategy in strategies {
+                        let shares = amount.allocate_with(&ratios, strategy);
+                        assert_eq!(shares.len(), ratios.len());
+                        let total = shares
+                            .iter()
+                            .fold(Money::from_minor(0, currency), |acc, s| acc + s.clone());
+                        assert_eq!(total, amount, "{:?} split by {:?}", amount, ratios);
+                        assert!(shares
+                            .iter()
+                            .all(|s| Money::from_minor(s.to_minor_units(), currency) == *s));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn money_formats_with_minor_units() {
+        let usd = Money::from_major(10, Currency::get(USD));
+        assert_eq!(usd.to_string(), "$10");
+        assert_eq!(usd.format_with_symbol(), "$10.00");
+        assert_eq!(usd.format_with_code(), "10.00 USD");
+        assert_eq!(money!("-1000.5", "GBP").format_with_symbol(), "-£1,000.50");
+        assert_eq!(money!("-1000.5", "GBP").format_with_code(), "-1000.50 GBP");
+        assert_eq!(money!(10, "JPY").format_with_code(), "10 JPY");
+        assert_eq!(money!("2.125", "USD").format_with_code(), "2.12 USD");
+    }
+
+    #[test]
+    fn money_formats_grouped_digits() {
+        assert_eq!(
+            money!(1_000_000, "USD").format_grouped(),
+            "1,000,000.00 USD"
+        );
+        assert_eq!(money!("-1234.5", "GBP").format_grouped(), "-1,234.50 GBP");
+        assert_eq!(money!(999, "USD").format_grouped(), "999.00 USD");
+        assert_eq!(money!("1.000,5", "EUR").format_grouped(), "1.000,50 EUR");
+        assert_eq!(money!(1_000_000, "JPY").format_grouped(), "1,000,000 JPY");
+    }
+
+    #[test]
+    fn money_parses_and_formats_euros() {
+        let euros = Money::from_string("1.234,50".to_string(), "EUR".to_string()).unwrap();
+        assert_eq!(euros, Money::from_minor(123450, Currency::get(EUR)));
+        assert_eq!(euros.currency().symbol, "€");
+        assert_eq!(euros.currency().exponent, 2);
+        assert_eq!(euros.to_string(), "€1.234,50");
+
+        let reparsed = Money::from_string(euros.to_string().replace("€", ""), "EUR".to_string());
+        assert_eq!(reparsed.unwrap(), euros);
+    }
+
+    #[test]
+    fn money_from_string_checked_rejects_oversized_amounts() {
+        // Money::from_string overflows the stack parsing this, despite it being a valid amount.
+        let padded = format!("{}1", "0".repeat(10_000));
+        assert_eq!(
+            Money::from_string_checked(padded, "USD".to_string()),
+            Err(MoneyParseError::InvalidDigit)
+        );
+        assert_eq!(
+            Money::from_string_checked("1e10000".to_string(), "USD".to_string()),
+            Err(MoneyParseError::InvalidDigit)
+        );
+        assert_eq!(
+            Money::from_string_checked("99999999999".to_string(), "USD".to_string()),
+            Err(MoneyParseError::InvalidDigit)
+        );
+        assert_eq!(
+            Money::parse(&format!("{} USD", "9".repeat(MAX_AMOUNT_LENGTH + 1))),
+            Err(MoneyParseError::InvalidDigit)
+        );
+        assert_eq!(
+            Money::from_string_checked("1,000.50".to_string(), "USD".to_string()),
+            Ok(money!("1000.50", "USD"))
+        );
+    }
+
+    #[test]
+    fn money_from_string_checked_rejects_misplaced_signs() {
+        // Money::from_string panics on a signed fractional part rather than returning an error.
+        for amount in &["1.-5", "1.+5", "1-", "--1"] {
+            assert_eq!(
+                Money::from_string_checked(amount.to_string(), "USD".to_string()),
+                Err(MoneyParseError::InvalidDigit)
+            );
+        }
+        assert_eq!(Money::parse("1.-5 USD"), Err(MoneyParseError::InvalidDigit));
+        assert_eq!(
+            Money::from_string_checked("-1.50".to_string(), "USD".to_string()),
+            Ok(money!("-1.50", "USD"))
+        );
+        assert_eq!(
+            Money::from_string_checked("+2".to_string(), "USD".to_string()),
+            Ok(money!(2, "USD"))
+        );
+    }
+
+    #[test]
+    fn money_from_string_checked_reports_why_parsing_failed() {
+        let check = |amount: &str, currency: &str| {
+            Money::from_string_checked(amount.to_string(), currency.to_string())
+        };
+        assert_eq!(check("12a", "USD"), Err(MoneyParseError::InvalidDigit));
+        assert_eq!(
+            check("1.2.3", "USD"),
+            Err(MoneyParseError::MultipleDelimiters)
+        );
+        assert_eq!(
+            check("1,2,3", "EUR"),
+            Err(MoneyParseError::MultipleDelimiters)
+        );
+        assert_eq!(
+            check("1,000,5", "EUR"),
+            Err(MoneyParseError::MultipleDelimiters)
+        );
+        assert_eq!(check("1.000,5", "EUR"), Ok(money!("1.000,5", "EUR")));
+        assert_eq!(check("", "USD"), Err(MoneyParseError::EmptyAmount));
+        assert_eq!(check("  ", "USD"), Err(MoneyParseError::EmptyAmount));
+        assert_eq!(check("10", "XYZ"), Err(MoneyParseError::UnknownCurrency));
+        assert_eq!(
+            Money::from_string_with_rounding(
+                "1..5".to_string(),
+                "USD".to_string(),
+                Round::HalfEven
+            ),
+            Err(MoneyParseError::MultipleDelimiters)
+        );
+    }
+
+    #[test]
+    fn money_from_string_with_rounding_supports_bankers_rounding() {
+        let half_even = Money::from_string_with_rounding(
+            "2.125".to_string(),
+            "USD".to_string(),
+            Round::HalfEven,
+        )
+        .unwrap();
+        let half_up =
+            Money::from_string_with_rounding("2.125".to_string(), "USD".to_string(), Round::HalfUp)
+                .unwrap();
+        assert_eq!(half_even, money!("2.12", "USD"));
+        assert_eq!(half_up, money!("2.13", "USD"));
+    }
+
+    #[test]
+    fn money_parse_reads_amount_and_currency() {
+        assert_eq!(Money::parse("50 GBP").unwrap(), money!(50, "GBP"));
+        assert_eq!(
+            Money::parse("  12.50\tusd ").unwrap(),
+            money!("12.50", "USD")
+        );
+    }
+
+    #[test]
+    fn money_parse_rejects_malformed_input() {
+        assert_eq!(Money::parse("50"), Err(MoneyParseError::UnknownCurrency));
+        assert_eq!(
+            Money::parse("50 XYZ"),
+            Err(MoneyParseError::UnknownCurrency)
+        );
+        assert_eq!(
+            Money::parse("GBP 50"),
+            Err(MoneyParseError::UnknownCurrency)
+        );
+        assert_eq!(
+            Money::parse("50 GBP 10"),
+            Err(MoneyParseError::UnknownCurrency)
+        );
+        assert_eq!(Money::parse(""), Err(MoneyParseError::EmptyAmount));
+        assert_eq!(Money::parse("5x GBP"), Err(MoneyParseError::InvalidDigit));
+        assert_eq!(
+            Money::parse("5.0.0 GBP"),
+            Err(MoneyParseError::MultipleDelimiters)
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! Ledgers that track debts in several currencies at once, settling each currency on its own.
+use crate::{Ledger, MoneyExt, MultiPartyTransaction, SettleError, Transaction, TransactionError};
+use rust_decimal::Decimal;
+use rusty_money::{Currency, Money, Round};
+use std::collections::HashMap;
+
+/// Exchange rates between pairs of currencies, used to settle debts in several currencies in a
+/// single one.
+#[derive(Clone, Debug, Default)]
+pub struct ExchangeRates {
+    rates: HashMap<(&'static str, &'static str), Decimal>,
+}
+
+impl ExchangeRates {
+    /// Creates an empty set of exchange rates.
+    pub fn new() -> ExchangeRates {
+        ExchangeRates::default()
+    }
+
+    /// Sets how many units of `to` one unit of `from` is worth, replacing any previous rate.
+    pub fn set(&mut self, from: &'static Currency, to: &'static Currency, rate: Decimal) {
+        self.rates
+            .insert((from.iso_alpha_code, to.iso_alpha_code), rate);
+    }
+
+    /// Returns how many units of `to` one unit of `from` is worth. A currency is always worth
+    /// one of itself, and a rate that was only set in the other direction is inverted.
+    pub fn get(&self, from: &'static Currency, to: &'static Currency) -> Option<Decimal> {
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+        let (from, to) = (from.iso_alpha_code, to.iso_alpha_code);
+        match self.rates.get(&(from, to)) {
+            Some(rate) => Some(*rate),
+            None => self
+                .rates
+                .get(&(to, from))
+                .filter(|rate| !rate.is_zero())
+                .map(|rate| Decimal::ONE / rate),
+        }
+    }
+}
+
+/// A ledger that holds a separate `Ledger` for every currency it has seen, keyed by the currency's
+/// ISO alphabetic code. Transactions are routed to the ledger for their currency, which is created
+/// the first time the currency is used, and amounts are never netted across currencies.
+#[derive(Debug, Default)]
+pub struct MultiCurrencyLedger {
+    ledgers: HashMap<&'static str, Ledger>,
+}
+
+impl MultiCurrencyLedger {
+    /// Creates a new MultiCurrencyLedger
+    pub fn new() -> MultiCurrencyLedger {
+        MultiCurrencyLedger::default()
+    }
+
+    /// Accepts a transaction and adds it to the ledger for its currency.
+    pub fn add_transaction(&mut self, transaction: Transaction) -> Result<(), TransactionError> {
+        self.ledgers
+            .entry(transaction.amount.currency().iso_alpha_code)
+            .or_default()
+            .add_transaction(transaction)
+    }
+
+    /// Accepts a multi-party transaction and adds it to the ledger for its currency.
+    pub fn add_multi_party_transaction(
+        &mut self,
+        transaction: MultiPartyTransaction,
+    ) -> Result<(), TransactionError> {
+        self.ledgers
+            .entry(transaction.amount.currency().iso_alpha_code)
+            .or_default()
+            .add_multi_party_transaction(transaction)
+    }
+
+    /// Returns the ledger for a currency code, if any transactions have been added in it.
+    pub fn ledger(&self, currency: &str) -> Option<&Ledger> {
+        self.ledgers.get(currency)
+    }
+
+    /// Returns the codes of every currency that transactions have been added in, sorted.
+    pub fn currencies(&self) -> Vec<&'static str> {
+        let mut currencies: Vec<&'static str> = self.ledgers.keys().copied().collect();
+        currencies.sort_unstable();
+        currencies
+    }
+
+    /// Settles every currency's ledger independently, returning each one's payments keyed by its
+    /// currency code. Like `Ledger::settle`, the ledgers are left unchanged. Returns an error if
+    /// any currency's balances don't add up to zero.
+    pub fn settle(&self) -> Result<HashMap<&'static str, Vec<Transaction>>, SettleError> {
+        let mut payments = HashMap::new();
+        for (currency, ledger) in &self.ledgers {
+            payments.insert(*currency, ledger.settle()?);
+        }
+        Ok(payments)
+    }
+
+    /// Settles every currency at once in the target currency, converting each party's balance
+    /// with the given rates and rounding it to the target's minor units. The ledgers are left
+    /// unchanged. Returns an error if a rate is missing or any currency's balances don't add up to
+    /// zero.
+    ///
+    /// Conversion is lossy. Rounding can leave the converted balances a few minor units away from
+    /// zero, and that difference is absorbed by the largest creditor.
+    pub fn settle_converted(
+        &self,
+        rates: &ExchangeRates,
+        target: &'static Currency,
+    ) -> Result<Vec<Transaction>, SettleError> {
+        let mut combined = Ledger::new();
+        for ledger in self.ledgers.values() {
+            ledger.check_balanced()?;
+            for (party, balance) in ledger.iter() {
+                let rate = rates.get(balance.currency(), target).ok_or(
+                    SettleError::MissingExchangeRate {
+                        from: balance.currency(),
+                        to: target,
+                    },
+                )?;
+                let converted = Money::from_decimal(*balance.amount() * rate, target)
+                    .round(target.exponent, Round::HalfEven);
+                *combined
+                    .map
+                    .entry(party.to_string())
+                    .or_insert_with(|| Money::from_major(0, target)) += converted;
+            }
+        }
+
+        let residual = Money::try_sum(target, combined.map.values().cloned())
+            .expect("converted balances are all in the target currency");
+        let largest_creditor = combined
+            .to_vector()
+            .into_iter()
+            .filter(|(_, balance)| balance.is_positive())
+            .max_by(|a, b| a.1.amount().cmp(b.1.amount()).then_with(|| b.0.cmp(&a.0)))
+            .map(|(party, _)| party);
+        if let Some(creditor) = largest_creditor {
+            *combined.map.get_mut(&creditor).unwrap() -= residual;
+        }
+
+        combined.settle()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction;
+    use rusty_money::Iso;
+
+    #[test]
+    fn multi_currency_ledger_settles_each_currency_independently() {
+        let mut ledger = MultiCurrencyLedger::new();
+        for transaction in [
+            transaction!("A", "B", (20, "USD")),
+            transaction!("B", "C", (20, "USD")),
+            transaction!("C", "A", (15, "EUR")),
+            transaction!("B", "A", (5, "GBP")),
+        ] {
+            ledger.add_transaction(transaction).unwrap();
+        }
+        assert_eq!(ledger.currencies(), vec!["EUR", "GBP", "USD"]);
+        assert_eq!(ledger.ledger("USD").unwrap().history().len(), 2);
+
+        let payments = ledger.settle().unwrap();
+        assert_eq!(payments.len(), 3);
+        assert_eq!(payments["USD"], vec![transaction!("A", "C", (20, "USD"))]);
+        assert_eq!(payments["EUR"], vec![transaction!("C", "A", (15, "EUR"))]);
+        assert_eq!(payments["GBP"], vec![transaction!("B", "A", (5, "GBP"))]);
+        assert_eq!(ledger.settle().unwrap(), payments);
+    }
+
+    #[test]
+    fn multi_currency_ledger_never_nets_across_currencies() {
+        // A owes B in dollars and B owes A the same number of pounds, which would cancel out if
+        // the currencies were netted.
+        let mut ledger = MultiCurrencyLedger::new();
+        for transaction in [
+            transaction!("A", "B", (10, "USD")),
+            transaction!("B", "C", (4, "USD")),
+            transaction!("B", "A", (10, "GBP")),
+        ] {
+            ledger.add_transaction(transaction).unwrap();
+        }
+
+        let mut payments = ledger.settle().unwrap();
+        assert_eq!(payments.len(), 2);
+        payments.get_mut("USD").unwrap().sort();
+        assert_eq!(
+            payments["USD"],
+            vec![
+                transaction!("A", "B", (6, "USD")),
+                transaction!("A", "C", (4, "USD")),
+            ]
+        );
+        assert_eq!(payments["GBP"], vec![transaction!("B", "A", (10, "GBP"))]);
+    }
+
+    #[test]
+    fn multi_currency_ledger_settles_in_target_currency() {
+        let (usd, eur) = (Currency::get(Iso::USD), Currency::get(Iso::EUR));
+        let mut rates = ExchangeRates::new();
+        rates.set(eur, usd, Decimal::new(11, 1));
+        assert_eq!(
+            rates.get(usd, eur),
+            Some(Decimal::ONE / Decimal::new(11, 1))
+        );
+        assert_eq!(rates.get(usd, usd), Some(Decimal::ONE));
+
+        let mut ledger = MultiCurrencyLedger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "USD")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("B", "C", (10, "EUR")))
+            .unwrap();
+        let mut payments = ledger.settle_converted(&rates, usd).unwrap();
+        payments.sort();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "C", (10, "USD")),
+                transaction!("B", "C", (1, "USD"))
+            ]
+        );
+        assert_eq!(ledger.ledger("EUR").unwrap().iter().count(), 2);
+
+        ledger
+            .add_transaction(transaction!("A", "B", (10, "GBP")))
+            .unwrap();
+        assert!(matches!(
+            ledger.settle_converted(&rates, usd),
+            Err(SettleError::MissingExchangeRate { .. })
+        ));
+        assert_eq!(ledger.ledger("GBP").unwrap().iter().count(), 2);
+    }
+
+    #[test]
+    fn multi_currency_ledger_gives_rounding_residual_to_largest_creditor() {
+        let (usd, gbp) = (Currency::get(Iso::USD), Currency::get(Iso::GBP));
+        let mut rates = ExchangeRates::new();
+        rates.set(gbp, usd, Decimal::new(333, 3));
+
+        let mut ledger = MultiCurrencyLedger::new();
+        ledger
+            .add_transaction(transaction!("A", "B", (1, "GBP")))
+            .unwrap();
+        ledger
+            .add_transaction(transaction!("C", "B", (1, "GBP")))
+            .unwrap();
+        let mut payments = ledger.settle_converted(&rates, usd).unwrap();
+        payments.sort();
+        assert_eq!(
+            payments,
+            vec![
+                transaction!("A", "B", ("0.33", "USD")),
+                transaction!("C", "B", ("0.33", "USD"))
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Serde support for rusty_money's `Money` and `Currency`, which can't implement serde's traits
+//! themselves.
+//!
+//! Money is serialized as its amount in a string, so no precision is lost through a float,
+//! alongside its currency code, e.g. `{"amount": "29.99", "currency": "USD"}`. Currencies are
+//! serialized as their code.
+use crate::money::find_currency;
+use rust_decimal::Decimal;
+use rusty_money::Money;
+use serde::de::{Deserializer, Error};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+struct MoneyRef<'a>(&'a Money);
+
+impl Serialize for MoneyRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Money", 2)?;
+        state.serialize_field("amount", &self.0.amount().to_string())?;
+        state.serialize_field("currency", self.0.currency().iso_alpha_code)?;
+        state.end()
+    }
+}
+
+struct MoneyDe(Money);
+
+impl<'de> Deserialize<'de> for MoneyDe {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct MoneyRepr {
+            amount: String,
+            currency: String,
+        }
+
+        let repr = MoneyRepr::deserialize(deserializer)?;
+        let amount = Decimal::from_str(&repr.amount).map_err(D::Error::custom)?;
+        let currency = find_currency(&repr.currency).map_err(D::Error::custom)?;
+        Ok(MoneyDe(Money::from_decimal(amount, currency)))
+    }
+}
+
+pub(crate) fn serialize<S: Serializer>(money: &Money, serializer: S) -> Result<S::Ok, S::Error> {
+    MoneyRef(money).serialize(serializer)
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+    MoneyDe::deserialize(deserializer).map(|money| money.0)
+}
+
+pub(crate) fn serialize_option<S: Serializer>(
+    money: &Option<Money>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    money.as_ref().map(MoneyRef).serialize(serializer)
+}
+
+pub(crate) fn serialize_balances<S: Serializer>(
+    balances: &[(String, Money)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(
+        balances
+            .iter()
+            .map(|(party, money)| (party, MoneyRef(money))),
+    )
+}
+
+/// Serializes a map of balances keyed by party.
+pub(crate) mod map {
+    use super::{MoneyDe, MoneyRef};
+    use rusty_money::Money;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::HashMap;
+
+    pub(crate) fn serialize<S: Serializer>(
+        map: &HashMap<String, Money>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(map.iter().map(|(party, money)| (party, MoneyRef(money))))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, Money>, D::Error> {
+        let map: HashMap<String, MoneyDe> = HashMap::deserialize(deserializer)?;
+        Ok(map
+            .into_iter()
+            .map(|(party, money)| (party, money.0))
+            .collect())
+    }
+}
+
+/// Serializes an optional currency as its code.
+pub(crate) mod currency {
+    use crate::money::find_currency;
+    use rusty_money::Currency;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        currency: &Option<&'static Currency>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        currency.map(|c| c.iso_alpha_code).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<&'static Currency>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(code) => find_currency(&code).map(Some).map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
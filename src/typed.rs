@@ -0,0 +1,148 @@
+//! Ledgers that are fixed to a single currency at the type level, so that amounts in different
+//! currencies can't be mixed by mistake.
+use crate::{Ledger, SettleError, Transaction, TransactionError};
+use rusty_money::Iso::*;
+use rusty_money::{Currency, Money};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A zero-sized type that stands in for a single currency.
+pub trait CurrencyMarker {
+    /// Returns the currency that the marker stands for.
+    fn currency() -> &'static Currency;
+}
+
+macro_rules! currency_marker {
+    ($name:ident, $iso:ident) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name;
+
+        impl CurrencyMarker for $name {
+            fn currency() -> &'static Currency {
+                Currency::get($iso)
+            }
+        }
+    };
+}
+
+currency_marker!(Usd, USD);
+currency_marker!(Gbp, GBP);
+currency_marker!(Eur, EUR);
+currency_marker!(Jpy, JPY);
+
+/// Represents an amount of money in the currency given by the marker type `C`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedMoney<C: CurrencyMarker> {
+    money: Money,
+    currency: PhantomData<C>,
+}
+
+impl<C: CurrencyMarker> TypedMoney<C> {
+    /// Creates an amount from a whole number of major units (e.g. 10 -> 10.00 USD).
+    pub fn from_major(amount: i64) -> TypedMoney<C> {
+        TypedMoney {
+            money: Money::from_major(amount, C::currency()),
+            currency: PhantomData,
+        }
+    }
+
+    /// Creates an amount from a whole number of minor units (e.g. 1000 -> 10.00 USD).
+    pub fn from_minor(amount: i64) -> TypedMoney<C> {
+        TypedMoney {
+            money: Money::from_minor(amount, C::currency()),
+            currency: PhantomData,
+        }
+    }
+
+    /// Returns the amount as untyped Money.
+    pub fn money(&self) -> &Money {
+        &self.money
+    }
+}
+
+impl<C: CurrencyMarker> fmt::Display for TypedMoney<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.money.fmt(f)
+    }
+}
+
+/// A ledger whose balances are all in the currency given by the marker type `C`.
+///
+/// Ledgers in different currencies are different types, so mixing them is a compile time error
+/// rather than a panic. Use `Ledger` when the currency is only known at runtime.
+#[derive(Debug)]
+pub struct TypedLedger<C: CurrencyMarker> {
+    ledger: Ledger,
+    currency: PhantomData<C>,
+}
+
+impl<C: CurrencyMarker> TypedLedger<C> {
+    /// Creates a new TypedLedger
+    pub fn new() -> TypedLedger<C> {
+        TypedLedger {
+            ledger: Ledger::new(),
+            currency: PhantomData,
+        }
+    }
+
+    /// Records that the debtor owes the creditor the amount, and updates their balances.
+    pub fn add_transaction(
+        &mut self,
+        debtor: String,
+        creditor: String,
+        amount: TypedMoney<C>,
+    ) -> Result<(), TransactionError> {
+        let transaction = Transaction::new(debtor, creditor, amount.money)?;
+        self.ledger.add_transaction(transaction)
+    }
+
+    /// Returns the smallest possible set of transactions that will resolve all debts, or an error
+    /// if the balances don't add up to zero. The ledger is left unchanged.
+    pub fn settle(&self) -> Result<Vec<Transaction>, SettleError> {
+        self.ledger.settle()
+    }
+
+    /// Returns the underlying untyped ledger.
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+}
+
+impl<C: CurrencyMarker> Default for TypedLedger<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction;
+
+    #[test]
+    fn typed_ledger_settles_in_its_currency() {
+        let mut ledger: TypedLedger<Gbp> = TypedLedger::new();
+        ledger
+            .add_transaction("A".to_string(), "B".to_string(), TypedMoney::from_major(20))
+            .unwrap();
+        ledger
+            .add_transaction(
+                "B".to_string(),
+                "C".to_string(),
+                TypedMoney::from_minor(2000),
+            )
+            .unwrap();
+        assert_eq!(
+            ledger.settle().unwrap(),
+            vec![transaction!("A", "C", (20, "GBP"))]
+        );
+    }
+
+    #[test]
+    fn typed_ledger_rejects_invalid_transactions() {
+        let mut ledger: TypedLedger<Usd> = TypedLedger::new();
+        let result =
+            ledger.add_transaction("A".to_string(), "B".to_string(), TypedMoney::from_major(0));
+        assert!(result.is_err());
+    }
+}